@@ -1,11 +1,14 @@
 use bevy::prelude::*;
-use iyes_loopless::condition::IntoConditionalExclusiveSystem;
+use iyes_loopless::condition::IntoConditionalSystem;
 use iyes_loopless::prelude::AppLooplessStateExt;
 
 use crate::GameState;
 
 use crate::{
-    components::{Actor, Position, Pushable, TakingTurn, WantsToMove},
+    components::{
+        Actor, BlocksMovement, BlocksVision, Door, Position, Pushable, TakingTurn,
+        WantsToCloseDoors, WantsToMove,
+    },
     map::GameMap,
     motion_resolver::{MotionResolver, MoveAttempt},
     player::Player,
@@ -21,6 +24,12 @@ enum SystemLabels {
     MoveActors,
 }
 
+/// Action points required to open a [`Door`]
+const OPEN_DOOR_COST: u32 = 1;
+
+/// Action points required to close one or more [`Door`s](Door) at once
+const CLOSE_DOOR_COST: u32 = 1;
+
 /// Event messages signalling the cost of an action
 #[derive(Debug)]
 pub struct ActionCost {
@@ -36,15 +45,20 @@ impl Plugin for ActionPlugin {
                 move_actors
                     .run_in_state(GameState::Ticking)
                     .label(SystemLabels::MoveActors),
-            );
+            )
+            .add_system(close_doors.run_in_state(GameState::Ticking))
+            .add_system(update_door_blockers);
     }
 }
 
 /// Updates the [`Position`] component of all moving actors
+#[allow(clippy::too_many_arguments)]
 fn move_actors(
     movers: Query<(Entity, &WantsToMove), With<TakingTurn>>,
     mut chars: Query<&mut Position>,
     pushables: Query<Entity, With<Pushable>>,
+    mut doors: Query<&mut Door>,
+    player: Query<Entity, With<Player>>,
     mut map: ResMut<GameMap>,
     mut commands: Commands,
     mut costs: EventWriter<ActionCost>,
@@ -53,6 +67,27 @@ fn move_actors(
     for (e, mov) in movers.iter() {
         let p = chars.get(e).unwrap();
 
+        // The player opens closed doors by bumping into them instead of moving
+        let target = p + (mov.dx, mov.dy);
+        let closed_door = map
+            .xy_to_idx(target.x, target.y)
+            .ok()
+            .and_then(|idx| map.blocked_by[idx])
+            .and_then(|blocker| doors.get_mut(blocker).ok())
+            .filter(|door| !door.open);
+        if let (Some(mut door), true) = (closed_door, player.contains(e)) {
+            door.open = true;
+            costs.send(ActionCost {
+                actor: e,
+                cost: OPEN_DOOR_COST,
+            });
+            commands
+                .entity(e)
+                .remove::<WantsToMove>()
+                .remove::<TakingTurn>();
+            continue;
+        }
+
         let resolver = MotionResolver::default();
         if let Ok(next_pos) = resolver.resolve(
             MoveAttempt {
@@ -83,9 +118,61 @@ fn move_actors(
     }
 }
 
+/// Closes all open [`Door`s](Door) orthogonally next to actors that want to close them.
+/// Doors that something is standing in remain open.
+#[allow(clippy::type_complexity)]
+fn close_doors(
+    closers: Query<(Entity, &Position), (With<WantsToCloseDoors>, With<TakingTurn>)>,
+    mut doors: Query<(&Position, &mut Door)>,
+    map: Res<GameMap>,
+    mut commands: Commands,
+    mut costs: EventWriter<ActionCost>,
+) {
+    for (e, p) in closers.iter() {
+        let mut closed_any = false;
+        for (door_pos, mut door) in doors.iter_mut() {
+            let adjacent = p.x.abs_diff(door_pos.x) + p.y.abs_diff(door_pos.y) == 1;
+            let blocked = map
+                .xy_to_idx(door_pos.x, door_pos.y)
+                .map_or(true, |idx| map.blocked_by[idx].is_some());
+            if door.open && adjacent && !blocked {
+                door.open = false;
+                closed_any = true;
+            }
+        }
+        if closed_any {
+            costs.send(ActionCost {
+                actor: e,
+                cost: CLOSE_DOOR_COST,
+            });
+        }
+        commands
+            .entity(e)
+            .remove::<WantsToCloseDoors>()
+            .remove::<TakingTurn>();
+    }
+}
+
 /// Marks all non-player actors to make their next move
 fn enqueue_actors(actors: Query<Entity, (With<Actor>, Without<Player>)>, mut commands: Commands) {
     for a in actors.iter() {
         commands.entity(a).insert(TakingTurn);
     }
 }
+
+/// Makes [`Door`s](Door) block movement and sight only while they are closed
+fn update_door_blockers(doors: Query<(Entity, &Door), Changed<Door>>, mut commands: Commands) {
+    for (e, door) in doors.iter() {
+        if door.open {
+            commands
+                .entity(e)
+                .remove::<BlocksMovement>()
+                .remove::<BlocksVision>();
+        } else {
+            commands
+                .entity(e)
+                .insert(BlocksMovement)
+                .insert(BlocksVision);
+        }
+    }
+}
@@ -29,16 +29,8 @@ impl Position {
 
     /// Computes a maximum norm different between two [`Position`s](Position)
     pub fn distance(&self, other: &Position) -> u32 {
-        let dx = if self.x > other.x {
-            self.x - other.x
-        } else {
-            other.x - self.x
-        };
-        let dy = if self.y > other.y {
-            self.y - other.y
-        } else {
-            other.y - self.y
-        };
+        let dx = self.x.abs_diff(other.x);
+        let dy = self.y.abs_diff(other.y);
         dx.max(dy)
     }
 }
@@ -101,6 +93,10 @@ pub struct WantsToMove {
     pub dy: i32,
 }
 
+/// Signals an actor's intent to close all open [`Door`s](Door) next to it
+#[derive(Debug, Component)]
+pub struct WantsToCloseDoors;
+
 /// Marks an entity that may take actions on each tick
 #[derive(Debug, Component, Default)]
 pub struct Actor;
@@ -130,6 +126,16 @@ impl Viewshed {
 #[derive(Debug, Component)]
 pub struct BlocksMovement;
 
+/// Marks entities that block the line of sight
+#[derive(Debug, Component)]
+pub struct BlocksVision;
+
+/// A door that blocks movement and sight while it is closed
+#[derive(Debug, Component)]
+pub struct Door {
+    pub open: bool,
+}
+
 /// Marks entities that can be moved out of the way
 #[derive(Debug, Component)]
 pub struct Pushable;
@@ -0,0 +1,176 @@
+use clap::Parser;
+use serde::Serialize;
+
+use crate::{
+    level::{build_level, GeneratedLevel, LevelSettings},
+    map::{GameMap, TileType},
+    map_builder::{spawner::Spawnables, validation::InvalidMapError, MapRng},
+};
+
+/// Parameters of the `generate` subcommand in addition to the map related ones of the game
+#[derive(Parser, Debug)]
+pub struct GenerateArgs {
+    /// Number of consecutive levels to generate
+    #[clap(short = 'l', long = "levels", default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    levels: u32,
+
+    /// How to print the generated maps
+    #[clap(short = 'f', long = "format", value_enum, default_value = "ascii")]
+    format: OutputFormat,
+}
+
+/// Available output formats of the `generate` subcommand
+#[derive(Debug, clap::ValueEnum, Clone, Copy)]
+enum OutputFormat {
+    /// One block of characters per level, see [`to_ascii`]
+    Ascii,
+    /// A single array with one object per level, with the map in the same format as `ascii`
+    Json,
+}
+
+/// A single level as printed by [`OutputFormat::Json`]
+#[derive(Serialize)]
+struct LevelOutput {
+    depth: u32,
+    style: Option<String>,
+    width: u32,
+    height: u32,
+    /// Rows of the ASCII map, top row first
+    rows: Vec<String>,
+    starting_position: Option<(u32, u32)>,
+    spawns: Vec<SpawnOutput>,
+}
+
+#[derive(Serialize)]
+struct SpawnOutput {
+    x: u32,
+    y: u32,
+    spawn: Spawnables,
+}
+
+/// Generates the given number of consecutive levels just like a game started with the same
+/// settings would
+pub fn generate_levels(
+    settings: &LevelSettings,
+    count: u32,
+) -> impl Iterator<Item = Result<GeneratedLevel, InvalidMapError>> + '_ {
+    let mut rng: MapRng = rand::SeedableRng::seed_from_u64(settings.original_seed);
+    let mut style = None;
+    (1..=count).map(move |depth| {
+        let level = build_level(settings, depth, style, &mut rng);
+        if let Ok(level) = &level {
+            style = level.style;
+        }
+        level
+    })
+}
+
+/// Returns the generated level or exits if the builder chain failed to build a valid map for the given depth
+pub fn level_or_exit(level: Result<GeneratedLevel, InvalidMapError>, depth: u32) -> GeneratedLevel {
+    level.unwrap_or_else(|e| {
+        eprintln!("The builder chain cannot build a valid map for level {depth}: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Generates the levels and prints them to stdout
+pub fn run(args: &GenerateArgs, settings: &LevelSettings) {
+    let levels: Vec<LevelOutput> = generate_levels(settings, args.levels)
+        .zip(1..)
+        .map(|(level, depth)| to_output(depth, &level_or_exit(level, depth)))
+        .collect();
+
+    match args.format {
+        OutputFormat::Ascii => {
+            for level in levels {
+                let style = level.style.as_deref().unwrap_or("Chain config");
+                println!(
+                    "Level {} ({style}, {} x {}):",
+                    level.depth, level.width, level.height
+                );
+                for row in level.rows {
+                    println!("{row}");
+                }
+                println!();
+            }
+        }
+        OutputFormat::Json => {
+            let json =
+                serde_json::to_string_pretty(&levels).expect("Levels are always serializable!");
+            println!("{json}");
+        }
+    }
+}
+
+fn to_output(depth: u32, level: &GeneratedLevel) -> LevelOutput {
+    let mut spawns: Vec<SpawnOutput> = level
+        .metadata
+        .spawn_list
+        .iter()
+        .map(|(&(x, y), &spawn)| SpawnOutput { x, y, spawn })
+        .collect();
+    // Keep the output stable across runs despite the unordered spawn list
+    spawns.sort_by_key(|s| (s.y, s.x));
+
+    LevelOutput {
+        depth,
+        style: level.style.map(|style| format!("{style:?}")),
+        width: level.map.width,
+        height: level.map.height,
+        rows: to_ascii(&level.map, level.metadata.starting_position, &spawns),
+        starting_position: level.metadata.starting_position,
+        spawns,
+    }
+}
+
+/// Renders the map with the top row (highest y coordinate) first. Tiles use the same characters
+/// as hand-written map templates where possible, the start is marked with `@` and spawns with
+/// their [`Spawnables::glyph`].
+fn to_ascii(map: &GameMap, start: Option<(u32, u32)>, spawns: &[SpawnOutput]) -> Vec<String> {
+    let mut glyphs: Vec<char> = map.tiles.iter().map(tile_glyph).collect();
+    for s in spawns {
+        glyphs[map.xy_to_idx(s.x, s.y).unwrap()] = s.spawn.glyph();
+    }
+    if let Some((x, y)) = start {
+        glyphs[map.xy_to_idx(x, y).unwrap()] = '@';
+    }
+    glyphs
+        .chunks(map.width as usize)
+        .rev()
+        .map(|row| row.iter().collect())
+        .collect()
+}
+
+fn tile_glyph(tile: &TileType) -> char {
+    match tile {
+        TileType::Floor => '.',
+        TileType::Wall => '#',
+        TileType::Grass => '"',
+        TileType::ShallowWater => '~',
+        TileType::DeepWater => '=',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ascii() {
+        let mut map = GameMap::new(4, 3);
+        for idx in [5, 6] {
+            map.tiles[idx] = TileType::Floor;
+        }
+        let spawns = [SpawnOutput {
+            x: 2,
+            y: 1,
+            spawn: Spawnables::TreasureChest,
+        }];
+
+        let rows = to_ascii(&map, Some((1, 1)), &spawns);
+        assert_eq!(rows, ["####", "#@$#", "####"]);
+        map.tiles[1] = TileType::ShallowWater;
+        let rows = to_ascii(&map, None, &[]);
+        assert_eq!(rows, ["####", "#..#", "#~##"]);
+    }
+}
@@ -0,0 +1,291 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use iyes_loopless::prelude::*;
+
+use crate::{
+    level::BuildHistory,
+    map::{GameMap, TileType},
+    map_builder::{spawner::Spawnables, MapMetadata},
+    GameState,
+};
+
+/// Replays the [`BuildHistory`] of the current level, see [`GameState::ViewMapHistory`]
+#[derive(Debug)]
+pub struct HistoryViewerPlugin;
+
+impl Plugin for HistoryViewerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HistoryViewer::default())
+            .add_system(toggle_viewer)
+            .add_enter_system(GameState::ViewMapHistory, restart_replay)
+            .add_system(advance_frames.run_in_state(GameState::ViewMapHistory))
+            .add_system(history_window.run_in_state(GameState::ViewMapHistory));
+    }
+}
+
+/// Size of each tile in the history window in pixels
+const TILE_PIXELS: f32 = 10.0;
+
+/// Playback state of the history replay
+struct HistoryViewer {
+    /// Index of the displayed snapshot
+    frame: usize,
+    playing: bool,
+    frames_per_second: u32,
+    timer: Timer,
+    show_rooms: bool,
+    show_regions: bool,
+    show_spawns: bool,
+}
+
+impl Default for HistoryViewer {
+    fn default() -> Self {
+        HistoryViewer {
+            frame: 0,
+            playing: true,
+            frames_per_second: 10,
+            timer: Timer::from_seconds(0.1, true),
+            show_rooms: true,
+            show_regions: true,
+            show_spawns: true,
+        }
+    }
+}
+
+/// Switches between playing the current level and viewing its history
+fn toggle_viewer(
+    keys: Res<Input<KeyCode>>,
+    game_state: Res<CurrentState<GameState>>,
+    mut commands: Commands,
+) {
+    if keys.just_pressed(KeyCode::H) {
+        match game_state.0 {
+            GameState::WaitingForPlayer => {
+                commands.insert_resource(NextState(GameState::ViewMapHistory))
+            }
+            GameState::ViewMapHistory => {
+                commands.insert_resource(NextState(GameState::WaitingForPlayer))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Starts playing the history from its first snapshot
+fn restart_replay(mut viewer: ResMut<HistoryViewer>) {
+    viewer.frame = 0;
+    viewer.playing = true;
+    viewer.timer.reset();
+}
+
+/// Moves on to the next snapshot(s) while playing and pauses at the last one
+fn advance_frames(time: Res<Time>, mut viewer: ResMut<HistoryViewer>, history: Res<BuildHistory>) {
+    if !viewer.playing {
+        return;
+    }
+    let elapsed = viewer.timer.tick(time.delta()).times_finished() as usize;
+    let last = history.0.len().saturating_sub(1);
+    viewer.frame = (viewer.frame + elapsed).min(last);
+    if viewer.frame == last {
+        viewer.playing = false;
+    }
+}
+
+/// Shows the playback controls and the current snapshot
+fn history_window(
+    mut ctx: ResMut<EguiContext>,
+    mut viewer: ResMut<HistoryViewer>,
+    history: Res<BuildHistory>,
+    mut commands: Commands,
+) {
+    let viewer = &mut *viewer;
+    egui::Window::new("Map generation history").show(ctx.ctx_mut(), |ui| {
+        if history.0.is_empty() {
+            ui.label("No snapshots have been taken for this level.");
+            return;
+        }
+        let last = history.0.len() - 1;
+        viewer.frame = viewer.frame.min(last);
+
+        ui.horizontal(|ui| {
+            if ui.button("First").clicked() {
+                viewer.frame = 0;
+            }
+            if ui.button("Back").clicked() {
+                viewer.frame = viewer.frame.saturating_sub(1);
+                viewer.playing = false;
+            }
+            if ui
+                .button(if viewer.playing { "Pause" } else { "Play" })
+                .clicked()
+            {
+                // Replay from the start once the end has been reached
+                if !viewer.playing && viewer.frame == last {
+                    viewer.frame = 0;
+                }
+                viewer.playing = !viewer.playing;
+                viewer.timer.reset();
+            }
+            if ui.button("Step").clicked() {
+                viewer.frame = (viewer.frame + 1).min(last);
+                viewer.playing = false;
+            }
+            if ui.button("Last").clicked() {
+                viewer.frame = last;
+                viewer.playing = false;
+            }
+            if ui.button("Close").clicked() {
+                commands.insert_resource(NextState(GameState::WaitingForPlayer));
+            }
+        });
+        ui.add(egui::Slider::new(&mut viewer.frame, 0..=last).text("Snapshot"));
+        let speed = egui::Slider::new(&mut viewer.frames_per_second, 1..=60).text("Per second");
+        if ui.add(speed).changed() {
+            let duration = Duration::from_secs_f32(1.0 / viewer.frames_per_second as f32);
+            viewer.timer.set_duration(duration);
+        }
+
+        let (map, metadata) = &history.0[viewer.frame];
+        ui.horizontal(|ui| {
+            let rooms = metadata.rooms.as_ref().map_or(0, Vec::len);
+            let regions = metadata.regions.as_ref().map_or(0, Vec::len);
+            ui.checkbox(&mut viewer.show_rooms, format!("Rooms ({rooms})"));
+            ui.checkbox(&mut viewer.show_regions, format!("Regions ({regions})"));
+            let spawns = metadata.spawn_list.len();
+            ui.checkbox(&mut viewer.show_spawns, format!("Spawns ({spawns})"));
+        });
+        egui::ScrollArea::both().show(ui, |ui| draw_snapshot(ui, map, metadata, viewer));
+    });
+}
+
+/// Paints the tiles of a snapshot and overlays the selected parts of its metadata
+fn draw_snapshot(ui: &mut egui::Ui, map: &GameMap, metadata: &MapMetadata, viewer: &HistoryViewer) {
+    let size = egui::vec2(map.width as f32, map.height as f32) * TILE_PIXELS;
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let origin = response.rect.min;
+    // The map's y axis points upwards, unlike the screen's
+    let tile_rect = |(x, y): (u32, u32)| {
+        let offset = egui::vec2(x as f32, (map.height - 1 - y) as f32) * TILE_PIXELS;
+        egui::Rect::from_min_size(origin + offset, egui::Vec2::splat(TILE_PIXELS))
+    };
+
+    for (idx, tile) in map.tiles.iter().enumerate() {
+        let pos = map.idx_to_xy(idx).unwrap();
+        painter.rect_filled(tile_rect(pos), 0.0, tile_color(*tile));
+    }
+    if let Some(regions) = metadata.regions.as_ref().filter(|_| viewer.show_regions) {
+        for (i, region) in regions.iter().enumerate() {
+            let color = highlight_color(i);
+            for &pos in region.tiles() {
+                painter.rect_filled(tile_rect(pos), 0.0, color);
+            }
+        }
+        for region in regions {
+            painter.text(
+                tile_rect(region.centroid()).center(),
+                egui::Align2::CENTER_CENTER,
+                region.id,
+                egui::FontId::monospace(TILE_PIXELS),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+    if let Some(rooms) = metadata.rooms.as_ref().filter(|_| viewer.show_rooms) {
+        let fill = egui::Color32::from_rgba_unmultiplied(120, 160, 255, 60);
+        for room in rooms {
+            for pos in room.tiles() {
+                painter.rect_filled(tile_rect(pos), 0.0, fill);
+            }
+        }
+        let stroke = egui::Stroke::new(1.5, egui::Color32::LIGHT_BLUE);
+        for &(a, b) in metadata.room_links.iter().flatten() {
+            if let (Some(a), Some(b)) = (rooms.get(a), rooms.get(b)) {
+                let centers = [
+                    tile_rect(a.center()).center(),
+                    tile_rect(b.center()).center(),
+                ];
+                painter.line_segment(centers, stroke);
+            }
+        }
+    }
+    if viewer.show_spawns {
+        for (&pos, &spawn) in metadata.spawn_list.iter() {
+            let center = tile_rect(pos).center();
+            painter.circle_filled(center, 0.35 * TILE_PIXELS, spawn_color(spawn));
+        }
+    }
+    if let Some(pos) = metadata.starting_position {
+        let rect = tile_rect(pos);
+        painter.circle_filled(rect.center(), 0.45 * TILE_PIXELS, egui::Color32::GREEN);
+        painter.circle_stroke(
+            rect.center(),
+            0.45 * TILE_PIXELS,
+            (1.0, egui::Color32::WHITE),
+        );
+    }
+
+    if let Some(pointer) = response.hover_pos() {
+        let x = ((pointer.x - origin.x) / TILE_PIXELS) as u32;
+        let row = ((pointer.y - origin.y) / TILE_PIXELS) as u32;
+        let y = (map.height - 1).saturating_sub(row);
+        if let Ok(idx) = map.xy_to_idx(x, y) {
+            let spawn = metadata
+                .spawn_list
+                .get(&(x, y))
+                .map_or(String::new(), |spawn| format!(", {spawn:?}"));
+            let region = metadata
+                .regions
+                .iter()
+                .flatten()
+                .filter(|_| viewer.show_regions)
+                .find(|region| region.tiles().contains(&(x, y)));
+            let region_info = region.map_or(String::new(), |region| {
+                let bounds = region.bounding_box();
+                let rect = tile_rect((bounds.x1, bounds.y2 - 1))
+                    .union(tile_rect((bounds.x2 - 1, bounds.y1)));
+                painter.rect_stroke(rect, 0.0, (1.0, egui::Color32::WHITE));
+                let mut tags: Vec<String> =
+                    region.tags.iter().map(|tag| format!("{tag:?}")).collect();
+                tags.sort();
+                format!(
+                    "\nRegion {} [{}] next to {:?}",
+                    region.id,
+                    tags.join(", "),
+                    region.neighbors
+                )
+            });
+            response.on_hover_text(format!(
+                "({x}, {y}) {:?}{spawn}{region_info}",
+                map.tiles[idx]
+            ));
+        }
+    }
+}
+
+fn tile_color(tile: TileType) -> egui::Color32 {
+    match tile {
+        TileType::Floor => egui::Color32::from_gray(110),
+        TileType::Wall => egui::Color32::from_gray(30),
+        TileType::Grass => egui::Color32::from_rgb(60, 110, 40),
+        TileType::ShallowWater => egui::Color32::from_rgb(70, 110, 170),
+        TileType::DeepWater => egui::Color32::from_rgb(30, 50, 120),
+    }
+}
+
+fn spawn_color(spawn: Spawnables) -> egui::Color32 {
+    match spawn {
+        Spawnables::TreasureChest => egui::Color32::GOLD,
+        Spawnables::Turtle => egui::Color32::RED,
+        Spawnables::Platino => egui::Color32::from_rgb(200, 120, 255),
+        Spawnables::Door => egui::Color32::from_rgb(150, 90, 40),
+    }
+}
+
+/// Returns a translucent color that differs between neighboring indices
+fn highlight_color(index: usize) -> egui::Color32 {
+    // Golden ratio steps spread the hues evenly
+    let hue = (index as f32 * 0.618).fract();
+    egui::color::Hsva::new(hue, 0.8, 1.0, 0.35).into()
+}
@@ -4,7 +4,7 @@ use iyes_loopless::state::{CurrentState, NextState};
 use super::GameState;
 
 use crate::{
-    components::{TakingTurn, WantsToMove},
+    components::{TakingTurn, WantsToCloseDoors, WantsToMove},
     player::Player,
 };
 
@@ -22,6 +22,7 @@ impl Plugin for KeyboardInputPlugin {
 #[derive(Debug)]
 enum PlayerAction {
     Move { dx: i32, dy: i32 },
+    CloseDoors,
 }
 
 /// Map keyboard input to player actions and update the [`GameState`]
@@ -39,6 +40,8 @@ fn keyboard_event_handler(
         Some(PlayerAction::Move { dx: 0, dy: 1 })
     } else if keys.pressed(KeyCode::Down) {
         Some(PlayerAction::Move { dx: 0, dy: -1 })
+    } else if keys.pressed(KeyCode::C) {
+        Some(PlayerAction::CloseDoors)
     } else {
         None
     };
@@ -50,6 +53,7 @@ fn keyboard_event_handler(
 
             match action {
                 PlayerAction::Move { dx, dy } => commands.entity(e).insert(WantsToMove { dx, dy }),
+                PlayerAction::CloseDoors => commands.entity(e).insert(WantsToCloseDoors),
             };
             commands.insert_resource(NextState(GameState::Ticking));
         }
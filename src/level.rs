@@ -3,28 +3,91 @@ use iyes_loopless::prelude::AppLooplessStateExt;
 use rand::prelude::StdRng;
 
 use crate::{
-    components::Position, map::GameMap, map_builder::MapMetadata, player::Player, GameState,
+    components::Position,
+    map::GameMap,
+    map_builder::{
+        cellular_builder::{CellularAutomataBuilder, CellularPhase},
+        chain_config::ChainConfig,
+        random_table::RandomTable,
+        room::{Corner, RoomShape},
+        validation::{InvalidMapError, MapRequirements},
+        BuilderChain, HasInitial, MapHistory, MapMetadata, MapRng, Uninitialized,
+    },
+    player::Player,
+    GameState,
 };
 
 /// Plugin responsible for level generation and cleanup
 pub struct LevelPlugin {
-    pub builder: MapBuilder,
-    pub seed: u64,
+    pub settings: LevelSettings,
 }
 
 /// Settings used for level generation
+#[derive(Clone)]
 pub struct LevelSettings {
-    /// [`MapBuilder`] to use for level generation (identical for all levels)
+    /// [`MapBuilder`] to use for level generation, [`MapBuilder::Random`] picks a style per level
     pub builder: MapBuilder,
     /// Seed to use when restarting game (to allow a second try upon failing)
     pub original_seed: u64,
+    /// Map dimensions, potentially growing with the [`LevelDepth`]
+    pub map_size: MapSize,
+    /// Builder chain loaded from a config file, replaces the [`MapBuilder`] if present
+    pub chain: Option<ChainConfig>,
+}
+
+/// Map dimensions of the first level and how they grow on deeper levels
+#[derive(Debug, Clone, Copy)]
+pub struct MapSize {
+    pub width: u32,
+    pub height: u32,
+    /// Number of tiles added to both dimensions for each level after the first one.
+    /// Maps grow to at most twice their original dimensions.
+    pub growth: u32,
+}
+
+impl MapSize {
+    /// Returns the width and height of the map for the given (1-based) level depth
+    pub fn at_depth(&self, depth: u32) -> (u32, u32) {
+        let growth = self.growth.saturating_mul(depth.saturating_sub(1));
+        (
+            self.width.saturating_add(growth).min(2 * self.width),
+            self.height.saturating_add(growth).min(2 * self.height),
+        )
+    }
 }
 
+/// Number of the current level, starting with 1 for the first level of a game
+pub struct LevelDepth(pub u32);
+
+/// Style of the current level, i.e. the [`MapBuilder`] that [`MapBuilder::Random`] resolved to
+pub struct LevelStyle(pub Option<MapBuilder>);
+
+/// Snapshots taken while building the current level's map, e.g. to replay them for debugging
+pub struct BuildHistory(pub MapHistory);
+
 /// Available builder configs to choose from the command line
 #[derive(Debug, clap::ValueEnum, Clone, Copy)]
 pub enum MapBuilder {
     Rooms,
     Cellular,
+    Bsp,
+    Drunkard,
+    Dla,
+    Maze,
+    Wfc,
+    Voronoi,
+    Outdoor,
+    Symmetric,
+    /// Picks one of the other styles for each level
+    Random,
+}
+
+impl MapBuilder {
+    /// Returns true for styles that generate natural looking caves
+    fn is_cave(&self) -> bool {
+        use MapBuilder::*;
+        matches!(self, Cellular | Drunkard | Dla | Wfc | Symmetric)
+    }
 }
 
 /// System labels used for system ordering
@@ -38,23 +101,21 @@ pub struct MapRNG(pub StdRng);
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(LevelSettings {
-            builder: self.builder,
-            original_seed: self.seed,
-        })
-        // Insert dummy map data to make sure the resource exists
-        .insert_resource(GameMap::new(1, 1))
-        .insert_resource(MapMetadata::default())
-        .add_enter_system(GameState::StartGame, setup_game)
-        // TODO: Check if this could be an exit system to have a complete cleanup (commands applied) before generating a new map
-        .add_enter_system(
-            GameState::EnterNewLevel,
-            generate_level.label(SystemLabels::GenerateLevel),
-        )
-        .add_enter_system(
-            GameState::EnterNewLevel,
-            despawn_map_entities.before(SystemLabels::GenerateLevel),
-        );
+        app.insert_resource(self.settings.clone())
+            // Insert dummy map data to make sure the resource exists
+            .insert_resource(GameMap::new(1, 1))
+            .insert_resource(MapMetadata::default())
+            .insert_resource(BuildHistory(Vec::new()))
+            .add_enter_system(GameState::StartGame, setup_game)
+            // TODO: Check if this could be an exit system to have a complete cleanup (commands applied) before generating a new map
+            .add_enter_system(
+                GameState::EnterNewLevel,
+                generate_level.label(SystemLabels::GenerateLevel),
+            )
+            .add_enter_system(
+                GameState::EnterNewLevel,
+                despawn_map_entities.before(SystemLabels::GenerateLevel),
+            );
     }
 }
 
@@ -63,68 +124,363 @@ fn setup_game(mut commands: Commands, level_settings: Res<LevelSettings>) {
     // Reset map generation RNG to the same seed upon restarting the game
     let rng = rand::SeedableRng::seed_from_u64(level_settings.original_seed);
     commands.insert_resource(MapRNG(rng));
+    commands.insert_resource(LevelDepth(0));
+    commands.insert_resource(LevelStyle(None));
 }
 
 /// Generates a map and performs other setup steps necessary upon entering a level
+#[allow(clippy::too_many_arguments)]
 fn generate_level(
     lvl_settings: Res<LevelSettings>,
     mut rng: ResMut<MapRNG>,
+    mut depth: ResMut<LevelDepth>,
+    mut style: ResMut<LevelStyle>,
     mut res_map: ResMut<GameMap>,
     mut res_map_metadata: ResMut<MapMetadata>,
+    mut res_history: ResMut<BuildHistory>,
+    mut exit: EventWriter<bevy::app::AppExit>,
 ) {
+    depth.0 += 1;
+    let level = match build_level(&lvl_settings, depth.0, style.0, &mut rng.0) {
+        Ok(level) => level,
+        Err(e) => {
+            error!("The builder chain cannot build a valid map ({e}), exiting!");
+            exit.send(bevy::app::AppExit);
+            return;
+        }
+    };
+
+    style.0 = level.style;
+    *res_map = level.map;
+    *res_map_metadata = level.metadata;
+    res_history.0 = level.history;
+}
+
+/// Everything produced by [`build_level`]
+pub struct GeneratedLevel {
+    /// The style the map was built with, `None` for builder chains loaded from a config
+    pub style: Option<MapBuilder>,
+    pub map: GameMap,
+    pub metadata: MapMetadata,
+    pub history: MapHistory,
+}
+
+/// Builds the map of the level at the given (1-based) depth. For [`MapBuilder::Random`], the style
+/// is rolled based on the style of the `previous` level. Falls back to [`MapBuilder::Rooms`] if the
+/// selected style does not produce a valid map. A builder chain loaded from a config is never
+/// replaced, so its error is returned instead.
+pub fn build_level(
+    settings: &LevelSettings,
+    depth: u32,
+    previous: Option<MapBuilder>,
+    rng: &mut MapRng,
+) -> Result<GeneratedLevel, InvalidMapError> {
+    let (width, height) = settings.map_size.at_depth(depth);
+    let builder = BuilderChain::new(width, height).at_depth(depth);
+    let (style, builder) = if let Some(chain) = &settings.chain {
+        (None, chain.builder_chain(builder))
+    } else {
+        let style = match settings.builder {
+            MapBuilder::Random => random_style(previous, rng),
+            builder => builder,
+        };
+        let mut builder = builder_chain(style, builder, rng);
+        builder.require(requirements(style));
+        (Some(style), builder)
+    };
+    let (style, (map, metadata, history)) = match (style, builder.build_map(rng)) {
+        (style, Ok(built)) => (style, built),
+        (None, Err(e)) => return Err(e),
+        (Some(_), Err(e)) => {
+            warn!("Cannot build a valid map ({e}), falling back to rooms!");
+            let fallback = BuilderChain::new(width, height).at_depth(depth);
+            let mut fallback = builder_chain(MapBuilder::Rooms, fallback, rng);
+            // Corridors connect all rooms, so only a start and a reachable objective are needed
+            fallback.require(MapRequirements {
+                min_floor_ratio: 0.0,
+                min_rooms: 1,
+                min_objective_distance: 1,
+                creatures: 0..=usize::MAX,
+            });
+            let built = fallback
+                .build_map(rng)
+                .expect("Room maps always have a reachable objective!");
+            (Some(MapBuilder::Rooms), built)
+        }
+    };
+
+    Ok(GeneratedLevel {
+        style,
+        map,
+        metadata,
+        history,
+    })
+}
+
+/// Weighted map styles for [`MapBuilder::Random`]
+fn random_styles() -> RandomTable<MapBuilder> {
+    use MapBuilder::*;
+    RandomTable::new()
+        .add(Rooms, 4)
+        .add(Bsp, 3)
+        .add(Maze, 1)
+        .add(Cellular, 3)
+        .add(Drunkard, 2)
+        .add(Dla, 1)
+        .add(Wfc, 1)
+        .add(Voronoi, 2)
+        .add(Outdoor, 2)
+        .add(Symmetric, 1)
+}
+
+/// A rule deciding if a `candidate` style may follow a level of the `previous` style (if any)
+type StyleRule = fn(previous: Option<MapBuilder>, candidate: MapBuilder) -> bool;
+
+/// Rules that each level of [`MapBuilder::Random`] has to follow
+const STYLE_RULES: &[StyleRule] = &[no_consecutive_caves];
+
+/// Avoids repetitive gameplay on similar looking caves
+fn no_consecutive_caves(previous: Option<MapBuilder>, candidate: MapBuilder) -> bool {
+    !(previous.is_some_and(|p| p.is_cave()) && candidate.is_cave())
+}
+
+/// Number of times a style is rolled before falling back to [`MapBuilder::Rooms`]
+const MAX_STYLE_ROLLS: u32 = 20;
+
+/// Rolls a map style from [`random_styles`] that follows all [`STYLE_RULES`]
+fn random_style(previous: Option<MapBuilder>, rng: &mut MapRng) -> MapBuilder {
+    let styles = random_styles();
+    for _ in 0..MAX_STYLE_ROLLS {
+        if let Some(style) = styles.roll(rng) {
+            if STYLE_RULES.iter().all(|rule| rule(previous, style)) {
+                return style;
+            }
+        }
+    }
+    warn!("Cannot find a random map style following all rules, using rooms instead!");
+    MapBuilder::Rooms
+}
+
+/// Requirements for the maps of each style. The minimum floor ratios are based on the ratios reported
+/// by the `stats` subcommand, caves use the default to reject the occasional tiny cave.
+fn requirements(style: MapBuilder) -> MapRequirements {
+    use MapBuilder::*;
+    let min_floor_ratio = match style {
+        Rooms | Voronoi => 0.05,
+        Bsp => 0.15,
+        Drunkard | Dla => 0.2,
+        Maze | Outdoor => 0.4,
+        Cellular | Wfc | Symmetric | Random => return MapRequirements::default(),
+    };
+    MapRequirements {
+        min_floor_ratio,
+        ..Default::default()
+    }
+}
+
+/// Adds the initial builder and all modifiers of the given style to the chain
+fn builder_chain(
+    style: MapBuilder,
+    builder: BuilderChain<Uninitialized>,
+    rng: &mut MapRng,
+) -> BuilderChain<HasInitial> {
     use crate::map_builder::{
         arbitrary_starting_point::ArbitraryStartingPoint,
-        cellular_builder::CellularAutomataBuilder,
-        cull_unreachable::CullUnreachable,
-        general_objective_spawner::GeneralObjectiveSpawner,
-        region_based_builders::{DistanceFunction, RegionBasedSpawner, VoronoiRegion},
+        bsp_builder::BspRoomBuilder,
+        corridors::{
+            BspCorridors, NearestNeighborCorridors, NoisyCorridors, SpanningTreeCorridors,
+        },
+        dla_builder::{DlaBuilder, DlaMode},
+        drunkard_builder::{DrunkSpawnMode, DrunkardsWalkBuilder},
+        maze_builder::MazeBuilder,
+        noise_builder::NoiseTerrainBuilder,
+        simple_map_builder::SimpleMapBuilder,
+        symmetry::{Symmetry, SymmetryMode},
+        voronoi_builder::VoronoiCellBuilder,
+        wfc_builder::{WaveFunctionCollapseBuilder, WfcSource},
+    };
+
+    match style {
+        MapBuilder::Rooms => {
+            use rand::Rng;
+
+            let mut builder = builder.start_with(SimpleMapBuilder::new(10, 4, 12, room_shapes()));
+            // Vary how the rooms are connected to vary how easy it is to get around
+            builder.with(match rng.gen_range(0..3) {
+                0 => NearestNeighborCorridors::new(),
+                1 => SpanningTreeCorridors::new(2),
+                _ => NoisyCorridors::new(2, 8.0),
+            });
+            with_room_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Bsp => {
+            // Small maps need smaller partitions to be split at all
+            let (width, height) = builder.size();
+            let partition_size = (width.min(height) / 3).clamp(5, 12);
+            let mut builder =
+                builder.start_with(BspRoomBuilder::new(partition_size, 5, room_shapes()));
+            builder.with(BspCorridors::new());
+            with_room_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Cellular => {
+            let mut builder = builder.start_with(cellular_caves());
+            // First add a starting point
+            builder.with(ArbitraryStartingPoint::new());
+            with_region_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Drunkard => {
+            // The walkers already determine the starting point
+            let mut builder = builder.start_with(DrunkardsWalkBuilder::new(
+                DrunkSpawnMode::Random,
+                400,
+                0.5,
+                1,
+            ));
+            with_region_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Dla => {
+            // The seed area of the cave is used as the starting point
+            let mut builder = builder.start_with(DlaBuilder::new(DlaMode::WalkInwards, 0.3, 1));
+            with_region_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Maze => {
+            let mut builder = builder.start_with(MazeBuilder::new(0.3, 4, 4, 8));
+            with_room_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Wfc => {
+            // Learn the patterns from a cave generated on the fly
+            let sample = cellular_caves();
+            let mut builder = builder.start_with(WaveFunctionCollapseBuilder::new(
+                WfcSource::Builder(sample),
+                3,
+            ));
+            builder.with(ArbitraryStartingPoint::new());
+            with_region_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Voronoi => {
+            use crate::map_builder::{
+                cull_unreachable::{Connectivity, CullUnreachable},
+                distant_objective_spawner::{DistanceBand, DistantObjectiveSpawner},
+                prefab_vaults::PrefabVaults,
+                region_based_builders::{
+                    DistanceFunction, RegionBasedSpawner, RegionTagger, TaggingStrategy,
+                },
+                spawner::Spawnables,
+            };
+
+            // The Voronoi cells double as regions to spawn monsters into
+            let mut builder =
+                builder.start_with(VoronoiCellBuilder::new(25, DistanceFunction::Euclidean));
+            builder.with(ArbitraryStartingPoint::new());
+            builder.with(CullUnreachable::new(Connectivity::FourWay, None));
+            // Corridors through the cells vary a lot in length, so aim for a fixed path length instead
+            builder.with(DistantObjectiveSpawner::new(
+                Spawnables::TreasureChest,
+                DistanceBand::PathLength(40..=60),
+            ));
+            // Monsters gather in the largest cells
+            builder.with(RegionTagger::new(TaggingStrategy::Size, 0.2));
+            builder.with(RegionBasedSpawner::new(3));
+            builder.with(PrefabVaults::new(1));
+            builder
+        }
+        MapBuilder::Outdoor => {
+            let mut builder = builder.start_with(NoiseTerrainBuilder::new(16.0, -0.15, 0.25));
+            builder.with(ArbitraryStartingPoint::new());
+            with_region_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Symmetric => {
+            let mut builder = builder.start_with(cellular_caves());
+            builder.with(ArbitraryStartingPoint::new());
+            // Mirror the quarter around the starting point, disconnected parts are culled afterwards
+            builder.with(Symmetry::new(SymmetryMode::Both));
+            with_region_based_spawning(&mut builder);
+            builder
+        }
+        MapBuilder::Random => unreachable!("Random styles are resolved before building the chain!"),
+    }
+}
+
+/// Caves shared by the cellular automata based styles: the map is filled with 40% floor tiles,
+/// and any tile becomes a wall if none or more than half of its neighbors are walls
+fn cellular_caves() -> Box<CellularAutomataBuilder> {
+    let rule = "B05678/S05678".parse().expect("The cave rule is valid!");
+    CellularAutomataBuilder::new(0.4, vec![CellularPhase::new(rule, 10)], true)
+}
+
+/// Weighted room shapes for the room-based builders, mostly plain rectangles
+fn room_shapes() -> RandomTable<RoomShape> {
+    RandomTable::new()
+        .add(RoomShape::Rectangle, 6)
+        .add(RoomShape::RoundedRectangle, 2)
+        .add(RoomShape::Circle, 2)
+        .add(RoomShape::Cross, 2)
+        // The corner is randomized for every room
+        .add(RoomShape::LShape(Corner::BottomLeft), 2)
+}
+
+/// Adds the default modifiers for placing doors, the player, monsters, and the objective into generated rooms
+fn with_room_based_spawning(builder: &mut BuilderChain<HasInitial>) {
+    use crate::map_builder::{
+        door_placement::DoorPlacement,
+        prefab_vaults::PrefabVaults,
         room_based_builders::{
             PositionSelectionMode, RoomBasedObjectiveSpawner, RoomBasedSpawner,
             RoomBasedStartingPosition, RoomSelectionMode,
         },
-        simple_map_builder::SimpleMapBuilder,
         spawner::Spawnables,
-        BuilderChain,
     };
 
-    let builder = BuilderChain::new();
-    let builder = {
-        match lvl_settings.builder {
-            MapBuilder::Rooms => {
-                let mut builder = builder.start_with(SimpleMapBuilder::new(10, 4, 12));
-                builder.with(RoomBasedStartingPosition::new(
-                    RoomSelectionMode::First,
-                    PositionSelectionMode::Center,
-                ));
-                builder.with(RoomBasedSpawner::new(1));
-                builder.with(RoomBasedObjectiveSpawner::new(
-                    RoomSelectionMode::Last,
-                    PositionSelectionMode::Random,
-                    Spawnables::TreasureChest,
-                ));
-                builder
-            }
-            MapBuilder::Cellular => {
-                let mut builder =
-                    builder.start_with(CellularAutomataBuilder::new(10, 0.4, vec![0, 5, 6, 7, 8]));
-                // First add a starting point
-                builder.with(ArbitraryStartingPoint::new());
-                // Then remove unreachable squares
-                builder.with(CullUnreachable::new());
-                // Make sure that a treasure chest is spawned
-                builder.with(GeneralObjectiveSpawner::new(Spawnables::TreasureChest));
-                // Split the tiles into regions
-                builder.with(VoronoiRegion::new(10, DistanceFunction::Manhattan));
-                // Spawn monsters into the regions
-                builder.with(RegionBasedSpawner::new(3));
-                builder
-            }
-        }
+    builder.with(DoorPlacement::new(0.75));
+    builder.with(RoomBasedStartingPosition::new(
+        RoomSelectionMode::First,
+        PositionSelectionMode::Center,
+    ));
+    builder.with(RoomBasedSpawner::new(1));
+    builder.with(RoomBasedObjectiveSpawner::new(
+        RoomSelectionMode::Last,
+        PositionSelectionMode::Random,
+        Spawnables::TreasureChest,
+    ));
+    builder.with(PrefabVaults::new(1));
+}
+
+/// Adds the default modifiers for cave-like maps that already have a starting position: removes unreachable
+/// tiles, places the objective far away from the start, and spawns monsters into tagged Voronoi regions
+fn with_region_based_spawning(builder: &mut BuilderChain<HasInitial>) {
+    use crate::map_builder::{
+        cull_unreachable::{Connectivity, CullUnreachable},
+        distant_objective_spawner::{DistanceBand, DistantObjectiveSpawner},
+        prefab_vaults::PrefabVaults,
+        region_based_builders::{
+            DistanceFunction, RegionBasedSpawner, RegionTagger, TaggingStrategy, VoronoiRegion,
+        },
+        spawner::Spawnables,
     };
-    let (map, map_metadata) = builder.build_map(&mut rng.0);
 
-    *res_map = map;
-    *res_map_metadata = map_metadata;
+    // Remove unreachable squares
+    builder.with(CullUnreachable::new(Connectivity::FourWay, None));
+    // Make sure that a treasure chest is spawned among the farthest tiles from the start
+    builder.with(DistantObjectiveSpawner::new(
+        Spawnables::TreasureChest,
+        DistanceBand::Farthest(0.1),
+    ));
+    // Split the tiles into regions
+    builder.with(VoronoiRegion::new(10, DistanceFunction::Manhattan));
+    // Keep the start quiet and let monsters gather far away from it
+    builder.with(RegionTagger::new(TaggingStrategy::DistanceFromStart, 0.2));
+    // Spawn monsters into the regions
+    builder.with(RegionBasedSpawner::new(3));
+    // Add a set piece if it fits anywhere
+    builder.with(PrefabVaults::new(1));
 }
 
 /// Remove all entities on the current map
@@ -143,3 +499,55 @@ fn despawn_map_entities(
         commands.entity(e).remove::<Position>();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map_builder::chain_config::ChainConfigError;
+
+    #[test]
+    fn test_random_style_follows_rules() {
+        let mut rng = MapRng::seed_from_u64(42);
+        let mut previous = None;
+        for _ in 0..200 {
+            let style = random_style(previous, &mut rng);
+            assert!(!matches!(style, MapBuilder::Random));
+            assert!(STYLE_RULES.iter().all(|rule| rule(previous, style)));
+            previous = Some(style);
+        }
+    }
+
+    #[test]
+    fn test_chains_without_objective_are_rejected() {
+        let chain = ChainConfig::from_ron(
+            "(initial: Cellular(floor_likelihood: 0.4, phases: [(rule: \"B05678/S05678\", iterations: 10)]),
+              modifiers: [ArbitraryStartingPoint])",
+        );
+        assert!(matches!(chain, Err(ChainConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_failing_chains_are_not_replaced() {
+        // Caves never consist of walkable tiles only
+        let chain = ChainConfig::from_ron(
+            "(initial: Drunkard(spawn_mode: Random, lifetime: 400, floor_percent: 0.5, brush_size: 1),
+              modifiers: [GeneralObjectiveSpawner(objective: TreasureChest)],
+              requirements: (min_floor_ratio: 1.0))",
+        )
+        .unwrap();
+        let settings = LevelSettings {
+            builder: MapBuilder::Rooms,
+            original_seed: 0,
+            map_size: MapSize {
+                width: 40,
+                height: 30,
+                growth: 0,
+            },
+            chain: Some(chain),
+        };
+        let level = build_level(&settings, 1, None, &mut MapRng::seed_from_u64(0));
+        assert!(matches!(level, Err(InvalidMapError::NotEnoughFloor(_))));
+    }
+}
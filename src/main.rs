@@ -10,12 +10,38 @@ use clap::Parser;
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct CLIArgs {
+    /// Runs the game if omitted
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// What map builder to use
-    #[clap(short = 'm', long = "map", value_enum, default_value = "rooms")]
+    #[clap(
+        short = 'm',
+        long = "map",
+        value_enum,
+        default_value = "rooms",
+        global = true
+    )]
     map_builder: level::MapBuilder,
 
+    /// Width of the first level's map in tiles
+    #[clap(long = "width", default_value = "80", value_parser = map_size_parser(), global = true)]
+    width: u32,
+
+    /// Height of the first level's map in tiles
+    #[clap(long = "height", default_value = "53", value_parser = map_size_parser(), global = true)]
+    height: u32,
+
+    /// Number of tiles added to the map's width and height on each deeper level (up to twice the original size)
+    #[clap(short = 'g', long = "growth", default_value = "0", global = true)]
+    growth: u32,
+
+    /// RON file describing the builder chain to use instead of the map builder, see `assets/chains`
+    #[clap(long = "chain", global = true)]
+    chain: Option<std::path::PathBuf>,
+
     /// Seed for map building RNG
-    #[clap(short = 's', long = "seed", default_value = "42")]
+    #[clap(short = 's', long = "seed", default_value = "42", global = true)]
     rng_seed: u64,
 
     /// Flag to enable WorldInspector
@@ -23,6 +49,20 @@ struct CLIArgs {
     inspector: bool,
 }
 
+/// Subcommands that do not start the game
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Print generated maps without opening a window
+    Generate(generate::GenerateArgs),
+    /// Print statistics on the maps generated for a range of seeds without opening a window
+    Stats(stats::StatsArgs),
+}
+
+/// Rejects map dimensions below [`MIN_MAP_SIZE`](map_builder::MIN_MAP_SIZE)
+fn map_size_parser() -> impl clap::builder::TypedValueParser<Value = u32> {
+    clap::value_parser!(u32).range(map_builder::MIN_MAP_SIZE as i64..)
+}
+
 /// All possible game states
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum GameState {
@@ -36,6 +76,8 @@ pub enum GameState {
     GameOver,
     /// Starting a new game, e.g. upon launch or after a [`GameOver`](GameState::GameOver)
     StartGame,
+    /// Replaying the snapshots taken while building the current level's map, toggled with `H`
+    ViewMapHistory,
 }
 
 #[cfg(debug_assertions)]
@@ -45,7 +87,7 @@ struct DebugPlugin;
 #[cfg(debug_assertions)]
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(bevy::diagnostic::FrameTimeDiagnosticsPlugin::default())
+        app.add_plugin(bevy::diagnostic::FrameTimeDiagnosticsPlugin)
             .add_plugin(bevy::diagnostic::LogDiagnosticsPlugin::default());
     }
 }
@@ -62,6 +104,30 @@ impl Plugin for InspectorPlugin {
 
 fn main() {
     let args = CLIArgs::parse();
+    let chain = args.chain.as_ref().map(|path| {
+        map_builder::chain_config::ChainConfig::load(path).unwrap_or_else(|e| {
+            eprintln!("Cannot load the builder chain {}: {e}", path.display());
+            std::process::exit(1);
+        })
+    });
+    let level_settings = level::LevelSettings {
+        builder: args.map_builder,
+        original_seed: args.rng_seed,
+        map_size: level::MapSize {
+            width: args.width,
+            height: args.height,
+            growth: args.growth,
+        },
+        chain,
+    };
+
+    match args.command {
+        Some(Command::Generate(generate_args)) => {
+            return generate::run(&generate_args, &level_settings);
+        }
+        Some(Command::Stats(stats_args)) => return stats::run(&stats_args, &level_settings),
+        None => {}
+    }
 
     let mut app = App::new();
     app.add_plugins(DefaultPlugins)
@@ -76,11 +142,11 @@ fn main() {
         .add_plugin(game_state::GameStatePlugin)
         .add_plugin(map::MapPlugin)
         .add_plugin(level::LevelPlugin {
-            builder: args.map_builder,
-            seed: args.rng_seed,
+            settings: level_settings,
         })
         .add_plugin(render::RenderPlugin)
         .add_plugin(ui::UIPlugin)
+        .add_plugin(history_viewer::HistoryViewerPlugin)
         .add_plugin(spawner::SpawningPlugin)
         .add_plugin(actions::ActionPlugin)
         .add_plugin(monster_ai::AIPlugin)
@@ -102,6 +168,8 @@ fn main() {
 mod actions;
 mod components;
 mod game_state;
+mod generate;
+mod history_viewer;
 mod input_handler;
 mod level;
 mod map;
@@ -111,5 +179,6 @@ mod motion_resolver;
 mod player;
 mod render;
 mod spawner;
+mod stats;
 mod ui;
 mod visibility;
@@ -1,12 +1,34 @@
 use bevy::prelude::*;
+use pathfinding::directed::dijkstra::dijkstra_all;
+use std::collections::HashMap;
 
-use crate::components::{BlocksMovement, Position};
+use crate::components::{BlocksMovement, BlocksVision, Position};
 
 /// Available tile types
-#[derive(Debug, Clone, Copy, PartialEq, Component)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
 pub enum TileType {
     Floor,
     Wall,
+    Grass,
+    /// Water that is shallow enough to wade through
+    ShallowWater,
+    /// Water that cannot be crossed, but does not block vision
+    DeepWater,
+}
+
+impl TileType {
+    /// Returns true if creatures can move onto tiles of this type
+    pub fn is_walkable(&self) -> bool {
+        matches!(
+            self,
+            TileType::Floor | TileType::Grass | TileType::ShallowWater
+        )
+    }
+
+    /// Returns true if tiles of this type block the line of sight
+    pub fn is_opaque(&self) -> bool {
+        matches!(self, TileType::Wall)
+    }
 }
 
 /// Represents the concrete tile layout of the game Map
@@ -17,6 +39,8 @@ pub struct GameMap {
     pub tiles: Vec<TileType>,
     pub revealed: Vec<bool>,
     pub blocked: Vec<bool>,
+    /// Marks tiles that block the line of sight, either by their [`TileType`] or an entity on them
+    pub opaque: Vec<bool>,
     /// Lists the [Entities](Entity) which are blocking the corresponding tile
     pub blocked_by: Vec<Option<Entity>>,
     pub tile_content: Vec<Vec<Entity>>,
@@ -38,6 +62,7 @@ impl GameMap {
             tiles: vec![TileType::Wall; size],
             revealed: vec![false; size],
             blocked: vec![false; size],
+            opaque: vec![true; size],
             tile_content: vec![Vec::new(); size],
             blocked_by: vec![None; size],
         }
@@ -48,6 +73,11 @@ impl GameMap {
         (self.width as usize) * (self.height as usize)
     }
 
+    /// Returns the number of [`TileType::Floor`] tiles in this [`GameMap`]
+    pub fn count_floor(&self) -> usize {
+        self.tiles.iter().filter(|&&t| t == TileType::Floor).count()
+    }
+
     /// Transforms a linear index to the corresponding (x,y) position in the map
     pub fn idx_to_xy(&self, idx: usize) -> Result<(u32, u32), OutsideMapError> {
         if idx >= (self.width * self.height) as usize {
@@ -66,11 +96,12 @@ impl GameMap {
         Ok((self.width * y + x) as usize)
     }
 
-    /// Marks all blocked tiles based on [`TileType`]
+    /// Marks all blocked and opaque tiles based on [`TileType`]
     fn determine_blocked(&mut self) {
         for (i, tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
+            self.blocked[i] = !tile.is_walkable();
             self.blocked_by[i] = None;
+            self.opaque[i] = tile.is_opaque();
         }
     }
 
@@ -110,6 +141,34 @@ impl GameMap {
         }
     }
 
+    /// Returns the orthogonally adjacent tiles that are walkable
+    pub fn walkable_neighbors(&self, (x, y): (u32, u32)) -> Vec<(u32, u32)> {
+        // Wrapped coordinates lie outside the map and are skipped
+        [
+            (x + 1, y),
+            (x, y + 1),
+            (x, y.wrapping_sub(1)),
+            (x.wrapping_sub(1), y),
+        ]
+        .into_iter()
+        .filter(|&(x, y)| {
+            self.xy_to_idx(x, y)
+                .is_ok_and(|idx| self.tiles[idx].is_walkable())
+        })
+        .collect()
+    }
+
+    /// Returns the path lengths from `start` to all other walkable tiles reachable without diagonal moves.
+    /// The start itself is not included.
+    pub fn path_lengths(&self, start: (u32, u32)) -> HashMap<(u32, u32), u32> {
+        dijkstra_all::<_, u32, _, _>(&start, |&pos| {
+            self.walkable_neighbors(pos).into_iter().map(|p| (p, 1))
+        })
+        .into_iter()
+        .map(|(pos, (_, distance))| (pos, distance))
+        .collect()
+    }
+
     pub fn get_free_neighbors(&self, pos: &Position) -> Vec<Position> {
         let mut neighbors = Vec::new();
         for i in -1..=1 {
@@ -138,6 +197,7 @@ fn index_map(
     mut map: ResMut<GameMap>,
     things: Query<(Entity, &Position)>,
     blockers: Query<&BlocksMovement>,
+    vision_blockers: Query<&BlocksVision>,
 ) {
     map.determine_blocked();
     map.clear_content_index();
@@ -148,6 +208,9 @@ fn index_map(
                 map.blocked[idx] = true;
                 map.blocked_by[idx] = Some(e);
             }
+            if vision_blockers.get(e).is_ok() {
+                map.opaque[idx] = true;
+            }
 
             map.tile_content[idx].push(e);
         }
@@ -192,21 +255,21 @@ mod tests {
 
         assert_eq!(map.idx_to_xy(12), Err(OutsideMapError));
         assert_eq!(map.idx_to_xy(200), Err(OutsideMapError));
-        assert_eq!(map.idx_to_xy(usize::max_value()), Err(OutsideMapError));
+        assert_eq!(map.idx_to_xy(usize::MAX), Err(OutsideMapError));
     }
 
     #[cfg(debug_assertions)]
     #[test]
     #[should_panic]
     fn test_map_too_large_1() {
-        GameMap::new(u32::max_value(), 2);
+        GameMap::new(u32::MAX, 2);
     }
 
     #[cfg(debug_assertions)]
     #[test]
     #[should_panic]
     fn test_map_too_large_2() {
-        GameMap::new(u32::max_value() / 2, 3);
+        GameMap::new(u32::MAX / 2, 3);
     }
 
     #[cfg(debug_assertions)]
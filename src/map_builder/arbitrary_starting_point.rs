@@ -1,7 +1,7 @@
-use rand::Rng;
+use bevy::log::*;
+use rand::seq::SliceRandom;
 
 use super::{MapBuildData, MapModifier, MapRng};
-use crate::map::TileType;
 
 pub struct ArbitraryStartingPoint;
 
@@ -13,31 +13,13 @@ impl ArbitraryStartingPoint {
 
 impl MapModifier for ArbitraryStartingPoint {
     fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
-        let random_idx = rng.gen_range(0..build_data.map.length());
-        if let Some((starting_idx, _)) = build_data
-            .map
-            .tiles
-            .iter()
-            .enumerate()
-            .skip(random_idx)
-            .find(|&(_, t)| *t == TileType::Floor)
-        {
-            let start_pos = build_data.map.idx_to_xy(starting_idx).unwrap();
-            build_data.metadata.starting_position = Some(start_pos);
-        } else if let Some((starting_idx, _)) = build_data
-            .map
-            .tiles
-            .iter()
-            .enumerate()
-            .take(random_idx)
-            .rev()
-            .find(|&(_, t)| *t == TileType::Floor)
-        {
-            let start_pos = build_data.map.idx_to_xy(starting_idx).unwrap();
-            build_data.metadata.starting_position = Some(start_pos);
-        } else {
-            panic!("Cannot find a single floor tile as the starting position!");
-        }
+        let Some(&start_pos) = build_data.free_tiles().choose(rng) else {
+            warn!("Cannot find a single free tile as the starting position!");
+            return;
+        };
+        build_data
+            .reserve_starting_position(start_pos)
+            .expect("The starting position has been selected among free tiles!");
         build_data.take_snapshot();
     }
 }
@@ -0,0 +1,17 @@
+use crate::map::{GameMap, TileType};
+
+/// Carves floor tiles into a square brush centered on (x, y) that is symmetric in all directions,
+/// i.e. even brush sizes are rounded up to the next odd size. A brush size of 0 or 1 marks a single tile.
+/// The outermost tiles of the map are never modified.
+pub(super) fn paint(map: &mut GameMap, brush_size: u32, x: u32, y: u32) {
+    let half = brush_size / 2;
+    let x_range = x.saturating_sub(half).max(1)..=(x + half).min(map.width - 2);
+    let y_range = y.saturating_sub(half).max(1)..=(y + half).min(map.height - 2);
+    for by in y_range {
+        for bx in x_range.clone() {
+            if let Ok(idx) = map.xy_to_idx(bx, by) {
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+    }
+}
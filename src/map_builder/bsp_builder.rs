@@ -0,0 +1,213 @@
+use rand::Rng;
+use std::ops::Range;
+
+use super::{
+    random_table::RandomTable,
+    rect::Rect,
+    room::{Room, RoomShape},
+    simple_map_builder::apply_room_to_map,
+    InitialMapBuilder, MapBuildData, MapRng,
+};
+
+/// Recursively splits the map area (binary space partitioning) and places a single room into each leaf partition.
+/// Room shapes are rolled from a [`RandomTable`].
+/// For each split, the closest rooms on both of its sides are recorded in
+/// [`MapMetadata::room_links`](super::MapMetadata::room_links) to be connected by
+/// [`BspCorridors`](super::corridors::BspCorridors).
+pub struct BspRoomBuilder {
+    /// Partitions are only split further if both halves would be at least this large
+    min_partition_size: u32,
+    min_room_size: u32,
+    shapes: RandomTable<RoomShape>,
+}
+
+impl InitialMapBuilder for BspRoomBuilder {
+    fn build_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let map = &build_data.map;
+        // Leave the outermost tiles as walls
+        let area = Rect::new(0, 0, map.width - 1, map.height - 1);
+        let mut leaves = Vec::new();
+        let mut splits = Vec::new();
+        self.partition(rng, area, &mut leaves, &mut splits);
+
+        let mut rooms: Vec<Room> = Vec::with_capacity(leaves.len());
+        let mut room_of_leaf = Vec::with_capacity(leaves.len());
+        for leaf in leaves.iter() {
+            if let Some(room) = self.room_in_partition(rng, leaf) {
+                apply_room_to_map(&mut build_data.map, &room);
+                room_of_leaf.push(Some(rooms.len()));
+                rooms.push(room);
+                build_data.take_snapshot();
+            } else {
+                room_of_leaf.push(None);
+            }
+        }
+
+        let links = splits
+            .into_iter()
+            .filter_map(|(first, second)| {
+                let first_rooms = room_of_leaf[first].iter().flatten();
+                let second_rooms = room_of_leaf[second].iter().flatten();
+                first_rooms
+                    .flat_map(|&a| second_rooms.clone().map(move |&b| (a, b)))
+                    .min_by_key(|&(a, b)| center_distance(&rooms[a], &rooms[b]))
+            })
+            .collect();
+        build_data.metadata.rooms = Some(rooms);
+        build_data.metadata.room_links = Some(links);
+    }
+}
+
+fn center_distance(a: &Room, b: &Room) -> u32 {
+    let (ax, ay) = a.center();
+    let (bx, by) = b.center();
+    ax.abs_diff(bx) + ay.abs_diff(by)
+}
+
+impl BspRoomBuilder {
+    pub fn new(
+        min_partition_size: u32,
+        min_room_size: u32,
+        shapes: RandomTable<RoomShape>,
+    ) -> Box<BspRoomBuilder> {
+        Box::new(BspRoomBuilder {
+            min_partition_size,
+            min_room_size,
+            shapes,
+        })
+    }
+
+    /// Splits `area` along its longer side until no partition can be split any further and collects the leaves.
+    /// Each split is recorded as the ranges of leaves on both of its sides.
+    fn partition(
+        &self,
+        rng: &mut MapRng,
+        area: Rect,
+        leaves: &mut Vec<Rect>,
+        splits: &mut Vec<(Range<usize>, Range<usize>)>,
+    ) {
+        let can_split_x = area.width() >= 2 * self.min_partition_size;
+        let can_split_y = area.height() >= 2 * self.min_partition_size;
+
+        let split_vertically = match (can_split_x, can_split_y) {
+            (false, false) => {
+                leaves.push(area);
+                return;
+            }
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => match area.width().cmp(&area.height()) {
+                std::cmp::Ordering::Greater => true,
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Equal => rng.gen_bool(0.5),
+            },
+        };
+
+        let (first, second) = if split_vertically {
+            let split =
+                rng.gen_range(self.min_partition_size..=area.width() - self.min_partition_size);
+            (
+                Rect::new(area.x1, area.y1, split, area.height()),
+                Rect::new(
+                    area.x1 + split,
+                    area.y1,
+                    area.width() - split,
+                    area.height(),
+                ),
+            )
+        } else {
+            let split =
+                rng.gen_range(self.min_partition_size..=area.height() - self.min_partition_size);
+            (
+                Rect::new(area.x1, area.y1, area.width(), split),
+                Rect::new(
+                    area.x1,
+                    area.y1 + split,
+                    area.width(),
+                    area.height() - split,
+                ),
+            )
+        };
+        let start = leaves.len();
+        self.partition(rng, first, leaves, splits);
+        let middle = leaves.len();
+        self.partition(rng, second, leaves, splits);
+        splits.push((start..middle, middle..leaves.len()));
+    }
+
+    /// Selects a randomly sized room that fits into the given partition (sharing its outer walls at most)
+    fn room_in_partition(&self, rng: &mut MapRng, partition: &Rect) -> Option<Room> {
+        if partition.width() < self.min_room_size || partition.height() < self.min_room_size {
+            return None;
+        }
+        let w = rng.gen_range(self.min_room_size..=partition.width());
+        let h = rng.gen_range(self.min_room_size..=partition.height());
+        let x = rng.gen_range(partition.x1..=partition.x2 - w);
+        let y = rng.gen_range(partition.y1..=partition.y2 - h);
+        let shape = self.shapes.roll(rng).unwrap_or(RoomShape::Rectangle);
+        Some(Room::new(
+            shape.with_random_orientation(rng),
+            Rect::new(x, y, w, h),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map_builder::room::Corner;
+
+    #[test]
+    fn test_rooms_stay_in_partitions_and_links_pair_siblings() {
+        let shapes = RandomTable::new()
+            .add(RoomShape::Rectangle, 1)
+            .add(RoomShape::Circle, 1)
+            .add(RoomShape::LShape(Corner::TopLeft), 1);
+        let mut builder = BspRoomBuilder::new(8, 4, shapes);
+        for seed in 0..20 {
+            let mut rng = MapRng::seed_from_u64(seed);
+            // Replay the partitioning with the same random numbers the builder is going to use
+            let (mut leaves, mut splits) = (Vec::new(), Vec::new());
+            builder.partition(
+                &mut rng.clone(),
+                Rect::new(0, 0, 59, 39),
+                &mut leaves,
+                &mut splits,
+            );
+
+            let mut build_data = MapBuildData::new(60, 40);
+            builder.build_map(&mut rng, &mut build_data);
+            let rooms = build_data.metadata.rooms.expect("BSP builds record rooms");
+            let links = build_data
+                .metadata
+                .room_links
+                .expect("BSP builds record links");
+
+            // Rooms are created in the order of their leaves but leaves may remain empty
+            let leaf_of_room: Vec<usize> = rooms
+                .iter()
+                .map(|room| {
+                    let b = room.bounds();
+                    leaves
+                        .iter()
+                        .position(|l| b.x1 >= l.x1 && b.x2 <= l.x2 && b.y1 >= l.y1 && b.y2 <= l.y2)
+                        .expect("Every room lies within a leaf")
+                })
+                .collect();
+            assert!(leaf_of_room.windows(2).all(|w| w[0] < w[1]));
+
+            for &(a, b) in links.iter() {
+                let (leaf_a, leaf_b) = (leaf_of_room[a], leaf_of_room[b]);
+                assert!(
+                    splits
+                        .iter()
+                        .any(|(first, second)| first.contains(&leaf_a) && second.contains(&leaf_b)),
+                    "Seed {seed}: rooms {a} and {b} are not in sibling partitions"
+                );
+            }
+            assert!(!rooms.is_empty() && links.len() <= splits.len());
+        }
+    }
+}
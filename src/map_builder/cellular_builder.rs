@@ -1,24 +1,159 @@
 use rand::Rng;
+use serde::Deserialize;
+use std::str::FromStr;
 
 use super::{InitialMapBuilder, MapBuildData, MapRng};
-use crate::map::TileType;
+use crate::map::{GameMap, TileType};
 
+/// Tiles around a tile whose walls are counted by a [`CellularRule`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Neighborhood {
+    /// The 8 surrounding tiles
+    Moore,
+    /// The 24 tiles within a radius of 2
+    ExtendedMoore,
+}
+
+impl Neighborhood {
+    fn radius(&self) -> i64 {
+        match self {
+            Neighborhood::Moore => 1,
+            Neighborhood::ExtendedMoore => 2,
+        }
+    }
+
+    fn size(&self) -> u32 {
+        let width = 2 * self.radius() as u32 + 1;
+        width * width - 1
+    }
+}
+
+/// Birth/survival rule of a cellular automaton, with walls being the living cells.
+///
+/// Uses the standard B/S notation: `B5678/S45678` turns floor tiles with 5 to 8 wall neighbors
+/// into walls, and keeps walls with 4 to 8 wall neighbors, all other tiles become floor.
+/// A leading `R2/` selects the [`Neighborhood::ExtendedMoore`], e.g. `R2/B13-24/S12,14-24`.
+/// Neighbor counts are either single digits or, if there is a comma or dash, a comma-separated
+/// list of numbers and ranges.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CellularRule {
+    neighborhood: Neighborhood,
+    birth: Vec<u32>,
+    survival: Vec<u32>,
+}
+
+impl CellularRule {
+    /// Returns whether a tile becomes (or stays) a wall
+    pub fn becomes_wall(&self, is_wall: bool, wall_neighbors: u32) -> bool {
+        if is_wall {
+            self.survival.contains(&wall_neighbors)
+        } else {
+            self.birth.contains(&wall_neighbors)
+        }
+    }
+}
+
+impl FromStr for CellularRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (neighborhood, s) = match s.strip_prefix("R2/") {
+            Some(rest) => (Neighborhood::ExtendedMoore, rest),
+            None => (Neighborhood::Moore, s),
+        };
+        let (birth, survival) = s
+            .split_once('/')
+            .ok_or_else(|| format!("{s} lacks the '/' between birth and survival"))?;
+        let birth = birth
+            .strip_prefix('B')
+            .ok_or_else(|| format!("{birth} does not start with 'B'"))?;
+        let survival = survival
+            .strip_prefix('S')
+            .ok_or_else(|| format!("{survival} does not start with 'S'"))?;
+        Ok(CellularRule {
+            neighborhood,
+            birth: parse_counts(birth, neighborhood)?,
+            survival: parse_counts(survival, neighborhood)?,
+        })
+    }
+}
+
+impl TryFrom<String> for CellularRule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Parses the neighbor counts of a birth or survival condition, see [`CellularRule`]
+fn parse_counts(s: &str, neighborhood: Neighborhood) -> Result<Vec<u32>, String> {
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<u32>()
+            .map_err(|_| format!("{n} is not a neighbor count"))
+    };
+    let mut counts = Vec::new();
+    if s.contains([',', '-']) {
+        for part in s.split(',') {
+            match part.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (parse(first)?, parse(last)?);
+                    if first > last {
+                        return Err(format!("{part} is a reversed range"));
+                    }
+                    counts.extend(first..=last);
+                }
+                None => counts.push(parse(part)?),
+            }
+        }
+    } else {
+        for c in s.chars() {
+            counts.push(parse(&c.to_string())?);
+        }
+    }
+
+    match counts.iter().find(|&&n| n > neighborhood.size()) {
+        Some(n) => Err(format!(
+            "{neighborhood:?} has no more than {} tiles, not {n}",
+            neighborhood.size()
+        )),
+        None => Ok(counts),
+    }
+}
+
+/// Applies a [`CellularRule`] for a number of iterations
+#[derive(Debug, Clone, Deserialize)]
+pub struct CellularPhase {
+    pub rule: CellularRule,
+    pub iterations: u32,
+}
+
+impl CellularPhase {
+    pub fn new(rule: CellularRule, iterations: u32) -> Self {
+        CellularPhase { rule, iterations }
+    }
+}
+
+/// Randomly fills the map with floor tiles and then applies the [`CellularPhase`]s one after the other.
+/// With `stop_when_stable`, each phase ends early once an iteration does not change any tile.
 pub struct CellularAutomataBuilder {
-    iterations: i32,
     floor_likelihood: f64,
-    neighbors_for_wall: Vec<i32>,
+    phases: Vec<CellularPhase>,
+    stop_when_stable: bool,
 }
 
 impl CellularAutomataBuilder {
     pub fn new(
-        iterations: i32,
         floor_likelihood: f64,
-        neighbors_for_wall: Vec<i32>,
+        phases: Vec<CellularPhase>,
+        stop_when_stable: bool,
     ) -> Box<CellularAutomataBuilder> {
         Box::new(CellularAutomataBuilder {
-            iterations,
             floor_likelihood,
-            neighbors_for_wall,
+            phases,
+            stop_when_stable,
         })
     }
 }
@@ -39,50 +174,117 @@ impl InitialMapBuilder for CellularAutomataBuilder {
         build_data.take_snapshot();
 
         // Iterate cellular automata rules
-        for _i in 0..self.iterations {
-            let mut new_tiles = build_data.map.tiles.clone();
-
-            let height = build_data.map.height;
-            let width = build_data.map.width;
-            for y in 1..height - 1 {
-                for x in 1..width - 1 {
-                    let idx = build_data.map.xy_to_idx(x, y).unwrap();
-                    let mut neighbors = 0;
-                    if build_data.map.tiles[idx - 1] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                    if build_data.map.tiles[idx + 1] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                    if build_data.map.tiles[idx - width as usize] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                    if build_data.map.tiles[idx + width as usize] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                    if build_data.map.tiles[idx - (width as usize - 1)] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                    if build_data.map.tiles[idx - (width as usize + 1)] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                    if build_data.map.tiles[idx + (width as usize - 1)] == TileType::Wall {
-                        neighbors += 1;
-                    }
-                    if build_data.map.tiles[idx + (width as usize + 1)] == TileType::Wall {
-                        neighbors += 1;
-                    }
+        for phase in self.phases.iter() {
+            for _i in 0..phase.iterations {
+                let new_tiles = apply_rule(&build_data.map, &phase.rule);
+                if self.stop_when_stable && new_tiles == build_data.map.tiles {
+                    break;
+                }
+                build_data.map.tiles = new_tiles;
+                build_data.take_snapshot();
+            }
+        }
+    }
+}
 
-                    if self.neighbors_for_wall.contains(&neighbors) {
-                        new_tiles[idx] = TileType::Wall;
-                    } else {
-                        new_tiles[idx] = TileType::Floor;
+/// Returns the tiles after a single iteration of the rule. The outermost tiles of the map stay
+/// as they are, and tiles beyond the map count as walls.
+fn apply_rule(map: &GameMap, rule: &CellularRule) -> Vec<TileType> {
+    let radius = rule.neighborhood.radius();
+    let is_wall = |x: i64, y: i64| {
+        if x < 0 || y < 0 {
+            return true;
+        }
+        map.xy_to_idx(x as u32, y as u32)
+            .map_or(true, |idx| map.tiles[idx] == TileType::Wall)
+    };
+
+    let mut new_tiles = map.tiles.clone();
+    for y in 1..map.height.saturating_sub(1) {
+        for x in 1..map.width.saturating_sub(1) {
+            let (x, y) = (x as i64, y as i64);
+            let mut wall_neighbors = 0;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if (dx, dy) != (0, 0) && is_wall(x + dx, y + dy) {
+                        wall_neighbors += 1;
                     }
                 }
             }
+            let idx = map.xy_to_idx(x as u32, y as u32).unwrap();
+            new_tiles[idx] = if rule.becomes_wall(is_wall(x, y), wall_neighbors) {
+                TileType::Wall
+            } else {
+                TileType::Floor
+            };
+        }
+    }
+    new_tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() {
+        let rule: CellularRule = "B05678/S45678".parse().unwrap();
+        assert_eq!(rule.neighborhood, Neighborhood::Moore);
+        assert_eq!(rule.birth, [0, 5, 6, 7, 8]);
+        assert_eq!(rule.survival, [4, 5, 6, 7, 8]);
+
+        let rule: CellularRule = "R2/B13-15,20/S".parse().unwrap();
+        assert_eq!(rule.neighborhood, Neighborhood::ExtendedMoore);
+        assert_eq!(rule.birth, [13, 14, 15, 20]);
+        assert!(rule.survival.is_empty());
+
+        for invalid in [
+            "B9/S1",
+            "B1S1",
+            "S1/B1",
+            "R2/B1-25/S1",
+            "B1,x/S1",
+            "B5-3/S1",
+        ] {
+            assert!(invalid.parse::<CellularRule>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_apply_rule() {
+        // A single wall in the center of an otherwise empty 9x9 interior
+        let mut map = GameMap::new(11, 11);
+        for idx in 0..map.length() {
+            let (x, y) = map.idx_to_xy(idx).unwrap();
+            if (1..10).contains(&x) && (1..10).contains(&y) && (x, y) != (5, 5) {
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+        let walls = |tiles: &[TileType]| tiles.iter().filter(|&&t| t == TileType::Wall).count();
+        let border = walls(&map.tiles) - 1;
+
+        // Tiles next to the center wall are born, the center wall has no wall neighbors to survive.
+        // All other interior tiles either have no wall neighbors or at least three at the border.
+        let moore = apply_rule(&map, &"B1/S1".parse().unwrap());
+        assert_eq!(walls(&moore) - border, 8);
+        assert_eq!(moore[map.xy_to_idx(5, 5).unwrap()], TileType::Floor);
+        let extended = apply_rule(&map, &"R2/B1/S".parse().unwrap());
+        assert_eq!(walls(&extended) - border, 24);
+    }
 
-            build_data.map.tiles = new_tiles;
-            build_data.take_snapshot();
+    #[test]
+    fn test_stop_when_stable() {
+        // Only floor tiles remain after the first iteration
+        let rule: CellularRule = "B/S".parse().unwrap();
+        for (stop_when_stable, snapshots) in [(false, 11), (true, 2)] {
+            let mut build_data = MapBuildData::new(10, 10);
+            let mut rng = MapRng::seed_from_u64(0);
+            let phases = vec![CellularPhase::new(rule.clone(), 10)];
+            CellularAutomataBuilder::new(0.5, phases, stop_when_stable)
+                .build_map(&mut rng, &mut build_data);
+            assert_eq!(build_data.history.len(), snapshots);
         }
     }
 }
@@ -0,0 +1,761 @@
+//! Data-driven [`BuilderChain`]s described in RON files, see `assets/chains` for examples.
+//! Each variant mirrors the constructor of the corresponding builder or modifier.
+
+use serde::Deserialize;
+use std::{fmt, fs, path::Path};
+
+use super::{
+    arbitrary_starting_point::ArbitraryStartingPoint,
+    bsp_builder::BspRoomBuilder,
+    cellular_builder::{CellularAutomataBuilder, CellularPhase},
+    corridors::{BspCorridors, NearestNeighborCorridors, NoisyCorridors, SpanningTreeCorridors},
+    cull_unreachable::{Connectivity, CullUnreachable},
+    distant_objective_spawner::{DistanceBand, DistantObjectiveSpawner},
+    dla_builder::{DlaBuilder, DlaMode},
+    door_placement::DoorPlacement,
+    drunkard_builder::{DrunkSpawnMode, DrunkardsWalkBuilder},
+    general_objective_spawner::GeneralObjectiveSpawner,
+    maze_builder::MazeBuilder,
+    noise_builder::NoiseTerrainBuilder,
+    prefab_vaults::PrefabVaults,
+    random_table::RandomTable,
+    region_based_builders::{
+        DistanceFunction, RegionBasedSpawner, RegionTagger, TaggingStrategy, VoronoiRegion,
+    },
+    room::RoomShape,
+    room_based_builders::{
+        PositionSelectionMode, RoomBasedObjectiveSpawner, RoomBasedSpawner,
+        RoomBasedStartingPosition, RoomSelectionMode,
+    },
+    simple_map_builder::SimpleMapBuilder,
+    spawner::Spawnables,
+    symmetry::{Symmetry, SymmetryMode},
+    validation::MapRequirements,
+    voronoi_builder::VoronoiCellBuilder,
+    wfc_builder::{parse_sample, WaveFunctionCollapseBuilder, WfcSource},
+    BuilderChain, HasInitial, InitialMapBuilder, MapModifier, Uninitialized,
+};
+
+/// An initial builder followed by an ordered list of modifiers
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainConfig {
+    pub initial: InitialConfig,
+    #[serde(default)]
+    pub modifiers: Vec<ModifierConfig>,
+    /// Requirements of the finished map, missing fields use their defaults
+    #[serde(default)]
+    pub requirements: MapRequirements,
+}
+
+/// Parameters of the available [`InitialMapBuilder`]s
+#[derive(Debug, Clone, Deserialize)]
+pub enum InitialConfig {
+    Rooms {
+        max_rooms: u32,
+        min_size: u32,
+        max_size: u32,
+        /// Weighted room shapes, all rooms are rectangles if empty
+        #[serde(default)]
+        shapes: Vec<(RoomShape, i32)>,
+    },
+    Bsp {
+        min_partition_size: u32,
+        min_room_size: u32,
+        /// Weighted room shapes, all rooms are rectangles if empty
+        #[serde(default)]
+        shapes: Vec<(RoomShape, i32)>,
+    },
+    Cellular {
+        floor_likelihood: f64,
+        /// Rules in B/S notation applied one after the other, see [`CellularRule`](super::cellular_builder::CellularRule)
+        phases: Vec<CellularPhase>,
+        #[serde(default)]
+        stop_when_stable: bool,
+    },
+    Drunkard {
+        spawn_mode: DrunkSpawnMode,
+        lifetime: u32,
+        floor_percent: f32,
+        brush_size: u32,
+    },
+    Dla {
+        mode: DlaMode,
+        floor_percent: f32,
+        brush_size: u32,
+    },
+    Maze {
+        braid: f32,
+        num_rooms: u32,
+        min_room_size: u32,
+        max_room_size: u32,
+    },
+    Wfc {
+        source: WfcSourceConfig,
+        pattern_size: u32,
+    },
+    Voronoi {
+        number_of_cells: u32,
+        distance_function: DistanceFunction,
+    },
+    Noise {
+        feature_size: f32,
+        water_level: f32,
+        rock_level: f32,
+    },
+}
+
+/// Configures the [`WfcSource`] of a [`WaveFunctionCollapseBuilder`]
+#[derive(Debug, Clone, Deserialize)]
+pub enum WfcSourceConfig {
+    Builder(Box<InitialConfig>),
+    Sample(String),
+}
+
+/// Parameters of the available [`MapModifier`]s
+#[derive(Debug, Clone, Deserialize)]
+pub enum ModifierConfig {
+    ArbitraryStartingPoint,
+    RoomBasedStartingPosition {
+        room_mode: RoomSelectionMode,
+        pos_mode: PositionSelectionMode,
+    },
+    NearestNeighborCorridors,
+    SpanningTreeCorridors {
+        extra_loops: u32,
+    },
+    BspCorridors,
+    NoisyCorridors {
+        extra_loops: u32,
+        feature_size: f32,
+    },
+    DoorPlacement {
+        door_chance: f32,
+    },
+    Symmetry {
+        mode: SymmetryMode,
+    },
+    /// Culls with the default [`Connectivity`] and without reconnecting anything
+    CullUnreachable,
+    CullUnreachableWith {
+        #[serde(default)]
+        connectivity: Connectivity,
+        /// Unreachable areas of up to this many tiles are connected by a tunnel instead of removed
+        #[serde(default)]
+        reconnect_max_size: Option<usize>,
+    },
+    VoronoiRegion {
+        number_of_regions: u32,
+        distance_function: DistanceFunction,
+    },
+    RegionTagger {
+        strategy: TaggingStrategy,
+        fraction: f32,
+    },
+    RoomBasedSpawner {
+        max_spawns: u32,
+    },
+    RegionBasedSpawner {
+        max_spawns: u32,
+    },
+    RoomBasedObjectiveSpawner {
+        room_mode: RoomSelectionMode,
+        pos_mode: PositionSelectionMode,
+        objective: Spawnables,
+    },
+    DistantObjectiveSpawner {
+        objective: Spawnables,
+        band: DistanceBand,
+    },
+    GeneralObjectiveSpawner {
+        objective: Spawnables,
+    },
+    PrefabVaults {
+        max_vaults: u32,
+    },
+}
+
+/// Signals why a [`ChainConfig`] could not be loaded
+#[derive(Debug)]
+pub enum ChainConfigError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    /// The config could be parsed, but would not result in a working chain
+    Invalid(String),
+}
+
+impl fmt::Display for ChainConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainConfigError::Io(e) => write!(f, "cannot read the file: {e}"),
+            ChainConfigError::Parse(e) => write!(f, "cannot parse the file: {e}"),
+            ChainConfigError::Invalid(msg) => write!(f, "invalid builder chain: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ChainConfigError {}
+
+/// Map metadata that is required by some steps of a chain and provided by others
+#[derive(Debug, Default, Clone, Copy)]
+struct Provided {
+    rooms: bool,
+    room_links: bool,
+    regions: bool,
+    starting_position: bool,
+    objective: bool,
+}
+
+impl ChainConfig {
+    /// Reads, parses, and validates a chain from a RON file
+    pub fn load(path: &Path) -> Result<Self, ChainConfigError> {
+        let source = fs::read_to_string(path).map_err(ChainConfigError::Io)?;
+        Self::from_ron(&source)
+    }
+
+    /// Parses and validates a chain from a RON string
+    pub fn from_ron(source: &str) -> Result<Self, ChainConfigError> {
+        let config: ChainConfig = ron::from_str(source).map_err(ChainConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks all parameters and that every step of the chain is preceded by the steps providing
+    /// the metadata it needs, e.g. rooms or a starting position. Every chain has to place a
+    /// starting position and an objective.
+    fn validate(&self) -> Result<(), ChainConfigError> {
+        let mut provided = self.initial.validate()?;
+        for (i, modifier) in self.modifiers.iter().enumerate() {
+            modifier
+                .validate(&mut provided)
+                .map_err(|msg| ChainConfigError::Invalid(format!("modifier #{i} {msg}")))?;
+        }
+        if !provided.starting_position {
+            return Err(ChainConfigError::Invalid(
+                "no starting position is placed, add a starting point modifier".to_string(),
+            ));
+        }
+        if !provided.objective {
+            return Err(ChainConfigError::Invalid(
+                "no objective is placed, add an objective spawner".to_string(),
+            ));
+        }
+        let requirements = &self.requirements;
+        check_fraction("min_floor_ratio", requirements.min_floor_ratio)
+            .and_then(|_| {
+                if requirements.creatures.is_empty() {
+                    Err(format!(
+                        "{:?} creatures cannot be met",
+                        requirements.creatures
+                    ))
+                } else {
+                    Ok(())
+                }
+            })
+            .map_err(|msg| ChainConfigError::Invalid(format!("requirements: {msg}")))
+    }
+
+    /// Adds the configured initial builder and modifiers to the chain
+    pub fn builder_chain(&self, builder: BuilderChain<Uninitialized>) -> BuilderChain<HasInitial> {
+        let mut builder = builder.start_with(self.initial.build());
+        for modifier in self.modifiers.iter() {
+            builder.with(modifier.build());
+        }
+        builder.require(self.requirements.clone());
+        builder
+    }
+}
+
+/// Returns an error message if the value lies outside of [0, 1]
+fn check_fraction(name: &str, value: f32) -> Result<(), String> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(format!("{name} must lie between 0 and 1, but is {value}"))
+    }
+}
+
+/// Returns an error message if the range is empty
+fn check_min_max(name: &str, min: u32, max: u32) -> Result<(), String> {
+    if min <= max {
+        Ok(())
+    } else {
+        Err(format!(
+            "min_{name} ({min}) must not exceed max_{name} ({max})"
+        ))
+    }
+}
+
+/// Returns an error message if the sample cannot be parsed or does not fit a single pattern
+fn check_sample(sample: &str, pattern_size: u32) -> Result<(), String> {
+    let map = parse_sample(sample)?;
+    if map.width < pattern_size || map.height < pattern_size {
+        Err(format!(
+            "the sample map ({} x {}) is smaller than pattern_size ({pattern_size})",
+            map.width, map.height
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn shape_table(shapes: &[(RoomShape, i32)]) -> RandomTable<RoomShape> {
+    shapes
+        .iter()
+        .fold(RandomTable::new(), |table, &(shape, weight)| {
+            table.add(shape, weight)
+        })
+}
+
+fn check_shapes(shapes: &[(RoomShape, i32)]) -> Result<(), String> {
+    match shapes.iter().find(|(_, weight)| *weight < 0) {
+        Some((shape, weight)) => Err(format!("{shape:?} has a negative weight of {weight}")),
+        None => Ok(()),
+    }
+}
+
+impl InitialConfig {
+    /// Checks the parameters and returns the metadata provided by this builder
+    fn validate(&self) -> Result<Provided, ChainConfigError> {
+        let mut provided = Provided::default();
+        let checked = match self {
+            InitialConfig::Rooms {
+                min_size,
+                max_size,
+                shapes,
+                ..
+            } => {
+                provided.rooms = true;
+                check_min_max("size", *min_size, *max_size).and(check_shapes(shapes))
+            }
+            InitialConfig::Bsp {
+                min_partition_size,
+                min_room_size,
+                shapes,
+            } => {
+                provided.rooms = true;
+                provided.room_links = true;
+                if *min_partition_size == 0 {
+                    Err("min_partition_size must be positive".to_string())
+                } else if min_room_size > min_partition_size {
+                    Err(format!(
+                        "min_room_size ({min_room_size}) must not exceed min_partition_size ({min_partition_size})"
+                    ))
+                } else {
+                    check_shapes(shapes)
+                }
+            }
+            InitialConfig::Cellular {
+                floor_likelihood,
+                phases,
+                ..
+            } => check_fraction("floor_likelihood", *floor_likelihood as f32).and_then(|_| {
+                if phases.is_empty() {
+                    Err("at least one phase is required".to_string())
+                } else if let Some(i) = phases.iter().position(|phase| phase.iterations == 0) {
+                    Err(format!("phase #{i} requires at least one iteration"))
+                } else {
+                    Ok(())
+                }
+            }),
+            InitialConfig::Drunkard {
+                lifetime,
+                floor_percent,
+                ..
+            } => {
+                provided.starting_position = true;
+                if *lifetime == 0 {
+                    Err("lifetime must be positive".to_string())
+                } else {
+                    check_fraction("floor_percent", *floor_percent)
+                }
+            }
+            InitialConfig::Dla { floor_percent, .. } => {
+                provided.starting_position = true;
+                check_fraction("floor_percent", *floor_percent)
+            }
+            InitialConfig::Maze {
+                braid,
+                num_rooms,
+                min_room_size,
+                max_room_size,
+            } => {
+                provided.rooms = *num_rooms > 0;
+                check_fraction("braid", *braid).and(check_min_max(
+                    "room_size",
+                    *min_room_size,
+                    *max_room_size,
+                ))
+            }
+            InitialConfig::Wfc {
+                source,
+                pattern_size,
+            } => {
+                if let WfcSourceConfig::Builder(builder) = source {
+                    builder.validate()?;
+                }
+                if *pattern_size == 0 {
+                    Err("pattern_size must be positive".to_string())
+                } else if let WfcSourceConfig::Sample(sample) = source {
+                    check_sample(sample, *pattern_size)
+                } else {
+                    Ok(())
+                }
+            }
+            InitialConfig::Voronoi {
+                number_of_cells, ..
+            } => {
+                provided.regions = true;
+                if *number_of_cells == 0 {
+                    Err("number_of_cells must be positive".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            InitialConfig::Noise { feature_size, .. } => {
+                if *feature_size > 0.0 {
+                    Ok(())
+                } else {
+                    Err("feature_size must be positive".to_string())
+                }
+            }
+        };
+        checked
+            .map(|_| provided)
+            .map_err(|msg| ChainConfigError::Invalid(format!("initial builder: {msg}")))
+    }
+
+    fn build(&self) -> Box<dyn InitialMapBuilder> {
+        match self.clone() {
+            InitialConfig::Rooms {
+                max_rooms,
+                min_size,
+                max_size,
+                shapes,
+            } => SimpleMapBuilder::new(max_rooms, min_size, max_size, shape_table(&shapes)),
+            InitialConfig::Bsp {
+                min_partition_size,
+                min_room_size,
+                shapes,
+            } => BspRoomBuilder::new(min_partition_size, min_room_size, shape_table(&shapes)),
+            InitialConfig::Cellular {
+                floor_likelihood,
+                phases,
+                stop_when_stable,
+            } => CellularAutomataBuilder::new(floor_likelihood, phases, stop_when_stable),
+            InitialConfig::Drunkard {
+                spawn_mode,
+                lifetime,
+                floor_percent,
+                brush_size,
+            } => DrunkardsWalkBuilder::new(spawn_mode, lifetime, floor_percent, brush_size),
+            InitialConfig::Dla {
+                mode,
+                floor_percent,
+                brush_size,
+            } => DlaBuilder::new(mode, floor_percent, brush_size),
+            InitialConfig::Maze {
+                braid,
+                num_rooms,
+                min_room_size,
+                max_room_size,
+            } => MazeBuilder::new(braid, num_rooms, min_room_size, max_room_size),
+            InitialConfig::Wfc {
+                source,
+                pattern_size,
+            } => {
+                let source = match source {
+                    WfcSourceConfig::Builder(builder) => WfcSource::Builder(builder.build()),
+                    WfcSourceConfig::Sample(sample) => WfcSource::Sample(sample),
+                };
+                WaveFunctionCollapseBuilder::new(source, pattern_size)
+            }
+            InitialConfig::Voronoi {
+                number_of_cells,
+                distance_function,
+            } => VoronoiCellBuilder::new(number_of_cells, distance_function),
+            InitialConfig::Noise {
+                feature_size,
+                water_level,
+                rock_level,
+            } => NoiseTerrainBuilder::new(feature_size, water_level, rock_level),
+        }
+    }
+}
+
+impl ModifierConfig {
+    /// Checks the parameters and whether the metadata this modifier needs has been provided
+    /// before, then adds the metadata provided by this modifier
+    fn validate(&self, provided: &mut Provided) -> Result<(), String> {
+        let (requires_rooms, requires_start) = match self {
+            ModifierConfig::ArbitraryStartingPoint => (false, false),
+            ModifierConfig::RoomBasedStartingPosition { .. } => (true, false),
+            ModifierConfig::NearestNeighborCorridors
+            | ModifierConfig::SpanningTreeCorridors { .. }
+            | ModifierConfig::RoomBasedSpawner { .. }
+            | ModifierConfig::RoomBasedObjectiveSpawner { .. } => (true, false),
+            ModifierConfig::BspCorridors => {
+                if !provided.room_links {
+                    return Err(format!("{self:?} requires room links of a Bsp builder"));
+                }
+                (true, false)
+            }
+            ModifierConfig::NoisyCorridors { feature_size, .. } => {
+                if *feature_size <= 0.0 {
+                    return Err(format!("{self:?} requires a positive feature_size"));
+                }
+                (true, false)
+            }
+            ModifierConfig::DoorPlacement { door_chance } => {
+                check_fraction("door_chance", *door_chance)?;
+                (true, false)
+            }
+            ModifierConfig::Symmetry { .. }
+            | ModifierConfig::PrefabVaults { .. }
+            | ModifierConfig::GeneralObjectiveSpawner { .. } => (false, false),
+            ModifierConfig::CullUnreachable | ModifierConfig::CullUnreachableWith { .. } => {
+                (false, false)
+            }
+            ModifierConfig::VoronoiRegion {
+                number_of_regions, ..
+            } => {
+                if *number_of_regions == 0 {
+                    return Err(format!("{self:?} requires at least one region"));
+                }
+                (false, false)
+            }
+            ModifierConfig::RegionTagger { strategy, fraction } => {
+                check_fraction("fraction", *fraction)?;
+                if !provided.regions {
+                    return Err(format!(
+                        "{self:?} requires regions, add a VoronoiRegion modifier first"
+                    ));
+                }
+                (false, *strategy == TaggingStrategy::DistanceFromStart)
+            }
+            ModifierConfig::RegionBasedSpawner { .. } => {
+                if !provided.regions {
+                    return Err(format!(
+                        "{self:?} requires regions, add a VoronoiRegion modifier first"
+                    ));
+                }
+                (false, false)
+            }
+            ModifierConfig::DistantObjectiveSpawner { band, .. } => {
+                match band {
+                    DistanceBand::Farthest(fraction) => check_fraction("fraction", *fraction)?,
+                    DistanceBand::PathLength(range) if range.is_empty() => {
+                        return Err(format!("{self:?} has an empty path length window"));
+                    }
+                    DistanceBand::PathLength(_) => {}
+                }
+                (false, true)
+            }
+        };
+        if let ModifierConfig::RoomBasedObjectiveSpawner { objective, .. }
+        | ModifierConfig::DistantObjectiveSpawner { objective, .. }
+        | ModifierConfig::GeneralObjectiveSpawner { objective } = self
+        {
+            if !objective.is_objective() {
+                return Err(format!(
+                    "{self:?} requires an objective, e.g. a TreasureChest"
+                ));
+            }
+        }
+        if requires_rooms && !provided.rooms {
+            return Err(format!(
+                "{self:?} requires rooms, start with a Rooms, Bsp or Maze builder"
+            ));
+        }
+        if requires_start && !provided.starting_position {
+            return Err(format!(
+                "{self:?} requires a starting position, add a starting point modifier first"
+            ));
+        }
+
+        match self {
+            ModifierConfig::ArbitraryStartingPoint
+            | ModifierConfig::RoomBasedStartingPosition { .. } => provided.starting_position = true,
+            ModifierConfig::VoronoiRegion { .. } => provided.regions = true,
+            ModifierConfig::RoomBasedObjectiveSpawner { .. }
+            | ModifierConfig::DistantObjectiveSpawner { .. }
+            | ModifierConfig::GeneralObjectiveSpawner { .. } => provided.objective = true,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn build(&self) -> Box<dyn MapModifier> {
+        match self.clone() {
+            ModifierConfig::ArbitraryStartingPoint => ArbitraryStartingPoint::new(),
+            ModifierConfig::RoomBasedStartingPosition {
+                room_mode,
+                pos_mode,
+            } => RoomBasedStartingPosition::new(room_mode, pos_mode),
+            ModifierConfig::NearestNeighborCorridors => NearestNeighborCorridors::new(),
+            ModifierConfig::SpanningTreeCorridors { extra_loops } => {
+                SpanningTreeCorridors::new(extra_loops)
+            }
+            ModifierConfig::BspCorridors => BspCorridors::new(),
+            ModifierConfig::NoisyCorridors {
+                extra_loops,
+                feature_size,
+            } => NoisyCorridors::new(extra_loops, feature_size),
+            ModifierConfig::DoorPlacement { door_chance } => DoorPlacement::new(door_chance),
+            ModifierConfig::Symmetry { mode } => Symmetry::new(mode),
+            ModifierConfig::CullUnreachable => CullUnreachable::new(Connectivity::default(), None),
+            ModifierConfig::CullUnreachableWith {
+                connectivity,
+                reconnect_max_size,
+            } => CullUnreachable::new(connectivity, reconnect_max_size),
+            ModifierConfig::VoronoiRegion {
+                number_of_regions,
+                distance_function,
+            } => VoronoiRegion::new(number_of_regions, distance_function),
+            ModifierConfig::RegionTagger { strategy, fraction } => {
+                RegionTagger::new(strategy, fraction)
+            }
+            ModifierConfig::RoomBasedSpawner { max_spawns } => RoomBasedSpawner::new(max_spawns),
+            ModifierConfig::RegionBasedSpawner { max_spawns } => {
+                RegionBasedSpawner::new(max_spawns)
+            }
+            ModifierConfig::RoomBasedObjectiveSpawner {
+                room_mode,
+                pos_mode,
+                objective,
+            } => RoomBasedObjectiveSpawner::new(room_mode, pos_mode, objective),
+            ModifierConfig::DistantObjectiveSpawner { objective, band } => {
+                DistantObjectiveSpawner::new(objective, band)
+            }
+            ModifierConfig::GeneralObjectiveSpawner { objective } => {
+                GeneralObjectiveSpawner::new(objective)
+            }
+            ModifierConfig::PrefabVaults { max_vaults } => PrefabVaults::new(max_vaults),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_chains_are_valid() {
+        for source in [
+            include_str!("../../assets/chains/rooms.ron"),
+            include_str!("../../assets/chains/caves.ron"),
+        ] {
+            let config = ChainConfig::from_ron(source);
+            assert!(config.is_ok(), "{config:?}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_chains_are_rejected() {
+        let missing_rooms = "(
+            initial: Cellular(floor_likelihood: 0.4, phases: [(rule: \"B05/S05\", iterations: 10)]),
+            modifiers: [RoomBasedSpawner(max_spawns: 1)],
+        )";
+        assert!(matches!(
+            ChainConfig::from_ron(missing_rooms),
+            Err(ChainConfigError::Invalid(_))
+        ));
+
+        let missing_start = "(
+            initial: Rooms(max_rooms: 10, min_size: 4, max_size: 12),
+            modifiers: [
+                NearestNeighborCorridors,
+                DistantObjectiveSpawner(objective: TreasureChest, band: Farthest(0.1)),
+            ],
+        )";
+        assert!(matches!(
+            ChainConfig::from_ron(missing_start),
+            Err(ChainConfigError::Invalid(_))
+        ));
+
+        let culls = "(
+            initial: Drunkard(spawn_mode: Random, lifetime: 400, floor_percent: 0.5, brush_size: 1),
+            modifiers: [
+                CullUnreachable,
+                CullUnreachableWith(connectivity: EightWay),
+                CullUnreachableWith(reconnect_max_size: Some(20)),
+                GeneralObjectiveSpawner(objective: TreasureChest),
+            ],
+        )";
+        let config = ChainConfig::from_ron(culls);
+        assert!(config.is_ok(), "{config:?}");
+
+        for modifiers in [
+            "GeneralObjectiveSpawner(objective: TreasureChest)",
+            "ArbitraryStartingPoint",
+            "ArbitraryStartingPoint, GeneralObjectiveSpawner(objective: Door)",
+        ] {
+            let incomplete = format!(
+                "(
+                    initial: Cellular(floor_likelihood: 0.4, phases: [(rule: \"B05/S05\", iterations: 10)]),
+                    modifiers: [{modifiers}],
+                )"
+            );
+            assert!(matches!(
+                ChainConfig::from_ron(&incomplete),
+                Err(ChainConfigError::Invalid(_))
+            ));
+        }
+
+        for initial in [
+            "Bsp(min_partition_size: 0, min_room_size: 0)",
+            "Bsp(min_partition_size: 4, min_room_size: 5)",
+            "Drunkard(spawn_mode: Random, lifetime: 0, floor_percent: 0.5, brush_size: 1)",
+        ] {
+            let bad_initial = format!(
+                "(
+                    initial: {initial},
+                    modifiers: [GeneralObjectiveSpawner(objective: TreasureChest)],
+                )"
+            );
+            assert!(matches!(
+                ChainConfig::from_ron(&bad_initial),
+                Err(ChainConfigError::Invalid(_))
+            ));
+        }
+
+        let bad_range = "(initial: Rooms(max_rooms: 10, min_size: 12, max_size: 4))";
+        assert!(matches!(
+            ChainConfig::from_ron(bad_range),
+            Err(ChainConfigError::Invalid(_))
+        ));
+
+        for phases in ["", "(rule: \"B05/S05\", iterations: 0)"] {
+            let bad_phases =
+                format!("(initial: Cellular(floor_likelihood: 0.4, phases: [{phases}]))");
+            assert!(matches!(
+                ChainConfig::from_ron(&bad_phases),
+                Err(ChainConfigError::Invalid(_))
+            ));
+        }
+
+        for rule in ["B9/S9", "B5-3/S5"] {
+            let bad_rule = format!(
+                "(initial: Cellular(floor_likelihood: 0.4, phases: [(rule: \"{rule}\", iterations: 10)]))"
+            );
+            assert!(matches!(
+                ChainConfig::from_ron(&bad_rule),
+                Err(ChainConfigError::Parse(_))
+            ));
+        }
+
+        for sample in ["#####\\n#.x.#\\n#####", "", "###\\n#.#\\n###"] {
+            let bad_sample =
+                format!("(initial: Wfc(source: Sample(\"{sample}\"), pattern_size: 4))");
+            assert!(matches!(
+                ChainConfig::from_ron(&bad_sample),
+                Err(ChainConfigError::Invalid(_))
+            ));
+        }
+
+        let unknown_builder = "(initial: Dungeon(size: 3))";
+        assert!(matches!(
+            ChainConfig::from_ron(unknown_builder),
+            Err(ChainConfigError::Parse(_))
+        ));
+    }
+}
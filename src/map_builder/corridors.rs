@@ -0,0 +1,235 @@
+//! [`MapModifier`]s connecting the [`MapMetadata::rooms`](super::MapMetadata::rooms) of room-based maps
+
+use pathfinding::directed::astar::astar;
+use rand::Rng;
+
+use super::{
+    noise::GradientNoise, room::Room, simple_map_builder::apply_tunnel, MapBuildData, MapModifier,
+    MapRng,
+};
+use crate::map::{GameMap, TileType};
+
+/// Connects each room to the closest room that has not been visited yet, starting with the first room.
+/// Results in a single chain of mostly short corridors.
+pub struct NearestNeighborCorridors;
+
+impl NearestNeighborCorridors {
+    pub fn new() -> Box<Self> {
+        Box::new(Self)
+    }
+}
+
+impl MapModifier for NearestNeighborCorridors {
+    fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let rooms = get_rooms(build_data);
+        if rooms.is_empty() {
+            return;
+        }
+
+        let mut visited = vec![false; rooms.len()];
+        let mut current = 0;
+        visited[current] = true;
+        let mut links = Vec::with_capacity(rooms.len());
+        while let Some(next) = (0..rooms.len())
+            .filter(|&i| !visited[i])
+            .min_by_key(|&i| center_distance(&rooms[current], &rooms[i]))
+        {
+            links.push((current, next));
+            visited[next] = true;
+            current = next;
+        }
+        dig_dogleg_corridors(rng, build_data, &rooms, &links);
+    }
+}
+
+/// Connects all rooms along a minimum spanning tree of the distances between their centers, i.e.
+/// with the shortest corridors that still connect all rooms. Optionally adds the shortest of the
+/// remaining possible corridors to create loops.
+pub struct SpanningTreeCorridors {
+    extra_loops: u32,
+}
+
+impl SpanningTreeCorridors {
+    pub fn new(extra_loops: u32) -> Box<SpanningTreeCorridors> {
+        Box::new(SpanningTreeCorridors { extra_loops })
+    }
+}
+
+impl MapModifier for SpanningTreeCorridors {
+    fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let rooms = get_rooms(build_data);
+        let links = spanning_tree_links(&rooms, self.extra_loops);
+        dig_dogleg_corridors(rng, build_data, &rooms, &links);
+    }
+}
+
+/// Connects the rooms suggested in [`MapMetadata::room_links`](super::MapMetadata::room_links),
+/// i.e. the closest rooms of sibling partitions for a [`BspRoomBuilder`](super::bsp_builder::BspRoomBuilder)
+pub struct BspCorridors;
+
+impl BspCorridors {
+    pub fn new() -> Box<Self> {
+        Box::new(Self)
+    }
+}
+
+impl MapModifier for BspCorridors {
+    fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let rooms = get_rooms(build_data);
+        let links = build_data
+            .metadata
+            .room_links
+            .clone()
+            .expect("Need room links to connect sibling partitions!");
+        dig_dogleg_corridors(rng, build_data, &rooms, &links);
+    }
+}
+
+/// Connects the same rooms as [`SpanningTreeCorridors`], but with winding corridors that follow the
+/// cheapest path (A*) through a seeded noise field. Corridors prefer to reuse existing floor tiles.
+pub struct NoisyCorridors {
+    extra_loops: u32,
+    /// Approximate length of the corridors' bends in tiles
+    feature_size: f32,
+}
+
+impl NoisyCorridors {
+    pub fn new(extra_loops: u32, feature_size: f32) -> Box<NoisyCorridors> {
+        Box::new(NoisyCorridors {
+            extra_loops,
+            feature_size,
+        })
+    }
+}
+
+/// Maximum additional cost of a wall tile in the noise field of [`NoisyCorridors`]
+const NOISE_COST: f32 = 20.0;
+
+impl MapModifier for NoisyCorridors {
+    fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let rooms = get_rooms(build_data);
+        let links = spanning_tree_links(&rooms, self.extra_loops);
+        let noise = GradientNoise::new(rng);
+        let scale = self.feature_size.max(1.0);
+
+        for (a, b) in links {
+            let map = &build_data.map;
+            // Keep the outermost tiles of the map as walls
+            let is_interior =
+                |&(x, y): &(u32, u32)| x > 0 && y > 0 && x + 1 < map.width && y + 1 < map.height;
+            let cost = |(x, y): (u32, u32)| {
+                if map.tiles[map.xy_to_idx(x, y).unwrap()].is_walkable() {
+                    1
+                } else {
+                    let n = noise.fbm(x as f32 / scale, y as f32 / scale, 2);
+                    // Noise values lie roughly within [-0.7, 0.7]
+                    1 + ((n + 0.7).max(0.0) * NOISE_COST) as u32
+                }
+            };
+            let goal = rooms[b].center();
+            let path = astar(
+                &rooms[a].center(),
+                |&(x, y)| {
+                    [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                        .into_iter()
+                        .filter(is_interior)
+                        .map(|p| (p, cost(p)))
+                        .collect::<Vec<_>>()
+                },
+                |&(x, y)| x.abs_diff(goal.0) + y.abs_diff(goal.1),
+                |&p| p == goal,
+            );
+            if let Some((path, _)) = path {
+                for (x, y) in path {
+                    set_floor(&mut build_data.map, x, y);
+                }
+                build_data.take_snapshot();
+            }
+        }
+    }
+}
+
+fn get_rooms(build_data: &MapBuildData) -> Vec<Room> {
+    build_data
+        .metadata
+        .rooms
+        .clone()
+        .expect("Need rooms to connect with corridors!")
+}
+
+fn center_distance(a: &Room, b: &Room) -> u32 {
+    let (ax, ay) = a.center();
+    let (bx, by) = b.center();
+    ax.abs_diff(bx) + ay.abs_diff(by)
+}
+
+/// Selects the links of a minimum spanning tree between all rooms (Prim's algorithm) and adds the
+/// `extra_loops` shortest remaining links
+fn spanning_tree_links(rooms: &[Room], extra_loops: u32) -> Vec<(usize, usize)> {
+    if rooms.is_empty() {
+        return Vec::new();
+    }
+    let mut in_tree = vec![false; rooms.len()];
+    in_tree[0] = true;
+    let mut links = Vec::with_capacity(rooms.len() + extra_loops as usize);
+    while let Some((a, b)) = (0..rooms.len())
+        .filter(|&a| in_tree[a])
+        .flat_map(|a| (0..rooms.len()).map(move |b| (a, b)))
+        .filter(|&(_, b)| !in_tree[b])
+        .min_by_key(|&(a, b)| center_distance(&rooms[a], &rooms[b]))
+    {
+        links.push((a, b));
+        in_tree[b] = true;
+    }
+
+    let mut remaining: Vec<(usize, usize)> = (0..rooms.len())
+        .flat_map(|a| (a + 1..rooms.len()).map(move |b| (a, b)))
+        .filter(|&(a, b)| !links.contains(&(a, b)) && !links.contains(&(b, a)))
+        .collect();
+    remaining.sort_by_key(|&(a, b)| center_distance(&rooms[a], &rooms[b]));
+    links.extend(remaining.into_iter().take(extra_loops as usize));
+    links
+}
+
+/// Connects the centers of the linked rooms with L-shaped corridors
+fn dig_dogleg_corridors(
+    rng: &mut MapRng,
+    build_data: &mut MapBuildData,
+    rooms: &[Room],
+    links: &[(usize, usize)],
+) {
+    for &(a, b) in links {
+        let (x1, y1) = rooms[a].center();
+        let (x2, y2) = rooms[b].center();
+        apply_tunnel(&mut build_data.map, x1, y1, x2, y2, rng.gen_bool(0.5));
+        build_data.take_snapshot();
+    }
+}
+
+fn set_floor(map: &mut GameMap, x: u32, y: u32) {
+    if let Ok(idx) = map.xy_to_idx(x, y) {
+        map.tiles[idx] = TileType::Floor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builder::{rect::Rect, room::RoomShape};
+
+    #[test]
+    fn test_spanning_tree_links() {
+        // Four rooms in a row with a fifth one far below the first
+        let rooms: Vec<Room> = [(0, 0), (10, 0), (20, 0), (30, 0), (0, 40)]
+            .into_iter()
+            .map(|(x, y)| Room::new(RoomShape::Rectangle, Rect::new(x, y, 4, 4)))
+            .collect();
+
+        let links = spanning_tree_links(&rooms, 0);
+        assert_eq!(links, vec![(0, 1), (1, 2), (2, 3), (0, 4)]);
+
+        let links = spanning_tree_links(&rooms, 2);
+        assert_eq!(links.len(), 6);
+        assert_eq!(links[4..], [(0, 2), (1, 3)]);
+    }
+}
@@ -1,47 +1,213 @@
-use pathfinding::directed::dijkstra::dijkstra_all;
+use bevy::log::*;
+use serde::Deserialize;
+use std::collections::VecDeque;
 
 use super::{MapBuildData, MapModifier, MapRng};
-use crate::map::TileType;
+use crate::map::{GameMap, TileType};
 
-/// Builder that identifies all rechable tiles from a set starting position and forces all unreachable tiles to be walls
-/// TODO: Make this configurable to allow diagonal movement or not
-pub struct CullUnreachable;
+/// Which tiles count as adjacent when determining the connected areas of a map
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum Connectivity {
+    /// Only orthogonally adjacent tiles, matching the player's movement
+    #[default]
+    FourWay,
+    /// Diagonally adjacent tiles as well
+    EightWay,
+}
+
+impl Connectivity {
+    /// Returns the adjacent positions, wrapped coordinates lie outside the map
+    fn neighbors(&self, (x, y): (u32, u32)) -> Vec<(u32, u32)> {
+        let (left, down) = (x.wrapping_sub(1), y.wrapping_sub(1));
+        let mut neighbors = vec![(x + 1, y), (x, y + 1), (x, down), (left, y)];
+        if *self == Connectivity::EightWay {
+            neighbors.extend([(x + 1, y + 1), (x + 1, down), (left, y + 1), (left, down)]);
+        }
+        neighbors
+    }
+}
+
+/// Builder that identifies all tiles reachable from the starting position (or within the largest
+/// connected area if there is none) and forces all unreachable walkable tiles to be walls.
+/// Unreachable areas of up to `reconnect_max_size` tiles are connected to the reachable ones by
+/// a tunnel instead. The sizes of all removed areas are recorded in
+/// [`MapMetadata::culled_components`](super::MapMetadata::culled_components).
+pub struct CullUnreachable {
+    connectivity: Connectivity,
+    reconnect_max_size: Option<usize>,
+}
 
 impl CullUnreachable {
-    pub fn new() -> Box<Self> {
-        Box::new(Self)
+    pub fn new(connectivity: Connectivity, reconnect_max_size: Option<usize>) -> Box<Self> {
+        Box::new(Self {
+            connectivity,
+            reconnect_max_size,
+        })
     }
 }
 
 impl MapModifier for CullUnreachable {
     fn modify_map(&mut self, _rng: &mut MapRng, build_data: &mut MapBuildData) {
-        let start_pos = build_data
-            .metadata
-            .starting_position
-            .expect("Cannot determine unreachable areas without a starting position!");
-
-        let reachable = dijkstra_all::<_, i32, _, _>(&start_pos, |&(x, y)| {
-            vec![(x + 1, y), (x, y + 1), (x, y - 1), (x - 1, y)]
-                .into_iter()
-                .filter(|&(x, y)| {
-                    if let Ok(idx) = build_data.map.xy_to_idx(x, y) {
-                        build_data.map.tiles[idx] != TileType::Wall
-                    } else {
-                        false
-                    }
-                })
-                .map(|p| (p, 1))
+        let map = &build_data.map;
+        let components = connected_components(map, self.connectivity);
+        let start_component = build_data.metadata.starting_position.and_then(|(x, y)| {
+            let idx = map.xy_to_idx(x, y).ok()?;
+            components.iter().position(|c| c.contains(&idx))
         });
+        let kept = match start_component {
+            Some(kept) => kept,
+            None => {
+                info!("No reachable area around a starting position, keeping the largest one");
+                // Prefer the first of several equally large components
+                let Some(largest) = components.iter().map(Vec::len).max() else {
+                    return;
+                };
+                components
+                    .iter()
+                    .position(|c| c.len() == largest)
+                    .expect("The largest component exists!")
+            }
+        };
 
-        for idx in 0..build_data.map.length() {
-            let pos = build_data
-                .map
-                .idx_to_xy(idx)
-                .expect("Tile index {idx} is outside the map!");
-            if !reachable.contains_key(&pos) && pos != start_pos {
+        let mut connected = vec![false; map.length()];
+        for &idx in components[kept].iter() {
+            connected[idx] = true;
+        }
+        for (i, component) in components.iter().enumerate() {
+            if i == kept {
+                continue;
+            }
+            let reconnect = self
+                .reconnect_max_size
+                .is_some_and(|max| component.len() <= max);
+            if reconnect && dig_tunnel(&mut build_data.map, component, &mut connected) {
+                continue;
+            }
+            for &idx in component {
                 build_data.map.tiles[idx] = TileType::Wall;
             }
+            build_data.metadata.culled_components.push(component.len());
         }
         build_data.take_snapshot();
     }
 }
+
+/// Returns the indices of the walkable tiles in each connected area, ordered by their first index
+fn connected_components(map: &GameMap, connectivity: Connectivity) -> Vec<Vec<usize>> {
+    let mut visited = vec![false; map.length()];
+    let mut components = Vec::new();
+    for first in 0..map.length() {
+        if visited[first] || !map.tiles[first].is_walkable() {
+            continue;
+        }
+        visited[first] = true;
+        let mut component = Vec::new();
+        let mut queue = VecDeque::from([first]);
+        while let Some(idx) = queue.pop_front() {
+            component.push(idx);
+            let pos = map.idx_to_xy(idx).unwrap();
+            for (x, y) in connectivity.neighbors(pos) {
+                if let Ok(n) = map.xy_to_idx(x, y) {
+                    if !visited[n] && map.tiles[n].is_walkable() {
+                        visited[n] = true;
+                        queue.push_back(n);
+                    }
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+/// Digs the shortest orthogonal tunnel through the interior of the map from the `component` to
+/// any `connected` tile and marks the component and the tunnel as connected. Returns false if
+/// there is no such tunnel.
+fn dig_tunnel(map: &mut GameMap, component: &[usize], connected: &mut [bool]) -> bool {
+    let is_interior =
+        |(x, y): (u32, u32)| x > 0 && y > 0 && x + 1 < map.width && y + 1 < map.height;
+    let mut previous: Vec<Option<usize>> = vec![None; map.length()];
+    let mut visited = vec![false; map.length()];
+    let mut queue = VecDeque::new();
+    for &idx in component {
+        visited[idx] = true;
+        queue.push_back(idx);
+    }
+
+    while let Some(idx) = queue.pop_front() {
+        if connected[idx] {
+            // Walk back to the component, turning everything along the way into floor
+            let mut current = previous[idx];
+            while let Some(idx) = current {
+                if !map.tiles[idx].is_walkable() {
+                    map.tiles[idx] = TileType::Floor;
+                }
+                connected[idx] = true;
+                current = previous[idx];
+            }
+            for &idx in component {
+                connected[idx] = true;
+            }
+            return true;
+        }
+        let pos = map.idx_to_xy(idx).unwrap();
+        for n in Connectivity::FourWay.neighbors(pos) {
+            if !is_interior(n) {
+                continue;
+            }
+            let n = map.xy_to_idx(n.0, n.1).unwrap();
+            if !visited[n] {
+                visited[n] = true;
+                previous[n] = Some(idx);
+                queue.push_back(n);
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// Builds a 7x5 map with a large area on the left and two single tiles on the right, one of
+    /// them diagonally adjacent to the large area
+    fn build_data() -> MapBuildData {
+        let mut build_data = MapBuildData::new(7, 5);
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2), (3, 3), (5, 1)] {
+            let idx = build_data.map.xy_to_idx(x, y).unwrap();
+            build_data.map.tiles[idx] = TileType::Floor;
+        }
+        build_data
+    }
+
+    fn floor_tiles(build_data: &MapBuildData) -> usize {
+        let map = &build_data.map;
+        map.tiles.iter().filter(|t| t.is_walkable()).count()
+    }
+
+    #[test]
+    fn test_cull_unreachable() {
+        let mut rng = MapRng::seed_from_u64(0);
+
+        // Without a starting position, the largest area is kept
+        let mut four_way = build_data();
+        CullUnreachable::new(Connectivity::FourWay, None).modify_map(&mut rng, &mut four_way);
+        assert_eq!(floor_tiles(&four_way), 4);
+        assert_eq!(four_way.metadata.culled_components, [1, 1]);
+
+        let mut eight_way = build_data();
+        eight_way.reserve_starting_position((2, 2)).unwrap();
+        CullUnreachable::new(Connectivity::EightWay, None).modify_map(&mut rng, &mut eight_way);
+        assert_eq!(floor_tiles(&eight_way), 5);
+        assert_eq!(eight_way.metadata.culled_components, [1]);
+
+        // A tunnel of two tiles reaches (5, 1) and another single tile one reaches (3, 3)
+        let mut tunnels = build_data();
+        CullUnreachable::new(Connectivity::FourWay, Some(1)).modify_map(&mut rng, &mut tunnels);
+        assert_eq!(floor_tiles(&tunnels), 9);
+        assert!(tunnels.metadata.culled_components.is_empty());
+    }
+}
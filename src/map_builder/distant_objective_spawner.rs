@@ -0,0 +1,104 @@
+use bevy::log::*;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use std::ops::RangeInclusive;
+
+use super::{spawner::Spawnables, MapBuildData, MapModifier, MapRng};
+
+/// Selects the tiles an objective may be placed on by their path length from the starting position
+#[derive(Debug, Clone, Deserialize)]
+pub enum DistanceBand {
+    /// The given fraction of all reachable tiles that are farthest away, e.g. 0.1 for the farthest 10%
+    Farthest(f32),
+    /// All tiles with a path length within the given range
+    PathLength(RangeInclusive<u32>),
+}
+
+/// Places the objective on a random free tile within a [`DistanceBand`] around the starting position.
+/// Path lengths are determined by [`GameMap::path_lengths`](crate::map::GameMap::path_lengths), which keeps the number of steps needed to reach the objective comparable between levels.
+pub struct DistantObjectiveSpawner {
+    objective: Spawnables,
+    band: DistanceBand,
+}
+
+impl DistantObjectiveSpawner {
+    pub fn new(objective: Spawnables, band: DistanceBand) -> Box<Self> {
+        Box::new(Self { objective, band })
+    }
+}
+
+impl MapModifier for DistantObjectiveSpawner {
+    fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let Some(start_pos) = build_data.metadata.starting_position else {
+            warn!("Cannot place the objective without a starting position!");
+            return;
+        };
+        let distances: Vec<((u32, u32), u32)> = build_data
+            .map
+            .path_lengths(start_pos)
+            .into_iter()
+            .filter(|&(pos, _)| build_data.is_free(pos))
+            .collect();
+
+        let candidates = match select_band(distances.clone(), &self.band) {
+            candidates if candidates.is_empty() => {
+                warn!(
+                    "No free tile within {:?} of the starting position, using the farthest one instead!",
+                    self.band
+                );
+                select_band(distances, &DistanceBand::Farthest(0.0))
+            }
+            candidates => candidates,
+        };
+        let Some(&pos) = candidates.choose(rng) else {
+            warn!("Cannot find a single free tile reachable from the starting position!");
+            return;
+        };
+        build_data
+            .reserve_spawn(pos, self.objective)
+            .expect("The objective is only placed on free tiles!");
+        build_data.take_snapshot();
+    }
+}
+
+/// Returns all positions within the given band in a deterministic order.
+/// [`DistanceBand::Farthest`] always includes at least a single tile if there are any.
+fn select_band(mut distances: Vec<((u32, u32), u32)>, band: &DistanceBand) -> Vec<(u32, u32)> {
+    // The flood fill returns the tiles in arbitrary order
+    distances.sort_by_key(|&(pos, distance)| (distance, pos));
+    match band {
+        DistanceBand::Farthest(fraction) => {
+            let count = (distances.len() as f32 * fraction.clamp(0.0, 1.0)).ceil() as usize;
+            let skipped = distances.len().saturating_sub(count.max(1));
+            distances
+                .into_iter()
+                .skip(skipped)
+                .map(|(pos, _)| pos)
+                .collect()
+        }
+        DistanceBand::PathLength(range) => distances
+            .into_iter()
+            .filter(|(_, distance)| range.contains(distance))
+            .map(|(pos, _)| pos)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_band() {
+        let distances: Vec<((u32, u32), u32)> = (1..=20).rev().map(|d| ((d, 0), d)).collect();
+
+        let farthest = select_band(distances.clone(), &DistanceBand::Farthest(0.1));
+        assert_eq!(farthest, [(19, 0), (20, 0)]);
+        let farthest = select_band(distances.clone(), &DistanceBand::Farthest(0.0));
+        assert_eq!(farthest, [(20, 0)]);
+        let window = select_band(distances.clone(), &DistanceBand::PathLength(5..=7));
+        assert_eq!(window, [(5, 0), (6, 0), (7, 0)]);
+        let window = select_band(distances, &DistanceBand::PathLength(25..=30));
+        assert!(window.is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+use bevy::log::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use super::{brush::paint, InitialMapBuilder, MapBuildData, MapRng};
+use crate::map::{GameMap, TileType};
+
+/// Upper limit for the number of particles to avoid looping forever if the desired floor area cannot be reached
+const MAX_PARTICLES: u32 = 100_000;
+
+/// Number of particles that stick to the cave between two snapshots
+const SNAPSHOT_INTERVAL: u32 = 50;
+
+/// Determines how the particles move until they stick to the existing cave
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum DlaMode {
+    /// Particles start at a random point and stumble around until they hit the cave
+    WalkInwards,
+    /// Particles start at the center and stumble around until they leave the cave
+    WalkOutwards,
+    /// Particles start at a random point and move straight towards the center until they hit the cave
+    CentralAttractor,
+}
+
+/// Grows branching caves by diffusion-limited aggregation: randomly moving particles stick to a
+/// seed area at the center of the map. Sets the map center as the starting position.
+pub struct DlaBuilder {
+    mode: DlaMode,
+    /// Fraction of the map's interior that should become floor tiles
+    floor_percent: f32,
+    brush_size: u32,
+}
+
+impl DlaBuilder {
+    pub fn new(mode: DlaMode, floor_percent: f32, brush_size: u32) -> Box<DlaBuilder> {
+        Box::new(DlaBuilder {
+            mode,
+            floor_percent,
+            brush_size,
+        })
+    }
+
+    /// Moves a particle from a random point until it hits a floor tile and returns the last wall tile it passed
+    fn walk_inwards(&self, rng: &mut MapRng, map: &GameMap) -> (u32, u32) {
+        let mut pos = random_interior_point(rng, map);
+        let mut prev = pos;
+        while is_wall(map, pos) {
+            prev = pos;
+            pos = stumble(rng, map, pos);
+        }
+        prev
+    }
+
+    /// Moves a particle from the center until it hits a wall tile and returns that tile
+    fn walk_outwards(&self, rng: &mut MapRng, map: &GameMap) -> (u32, u32) {
+        let mut pos = (map.width / 2, map.height / 2);
+        while !is_wall(map, pos) {
+            pos = stumble(rng, map, pos);
+        }
+        pos
+    }
+
+    /// Moves a particle from a random point towards the center until it hits a floor tile and returns
+    /// the last wall tile it passed
+    fn central_attractor(&self, rng: &mut MapRng, map: &GameMap) -> (u32, u32) {
+        let center = (map.width / 2, map.height / 2);
+        let mut pos = random_interior_point(rng, map);
+        let mut prev = pos;
+        while is_wall(map, pos) && pos != center {
+            prev = pos;
+            // Only take orthogonal steps so the cave stays connected for orthogonal movement
+            let dx = pos.0.abs_diff(center.0);
+            let dy = pos.1.abs_diff(center.1);
+            if dx >= dy {
+                pos.0 = if pos.0 < center.0 {
+                    pos.0 + 1
+                } else {
+                    pos.0 - 1
+                };
+            } else {
+                pos.1 = if pos.1 < center.1 {
+                    pos.1 + 1
+                } else {
+                    pos.1 - 1
+                };
+            }
+        }
+        prev
+    }
+}
+
+impl InitialMapBuilder for DlaBuilder {
+    fn build_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let width = build_data.map.width;
+        let height = build_data.map.height;
+        let center = (width / 2, height / 2);
+
+        // Seed the cave with a small cross at the center
+        paint(&mut build_data.map, self.brush_size, center.0, center.1);
+        for (x, y) in [
+            (center.0 - 1, center.1),
+            (center.0 + 1, center.1),
+            (center.0, center.1 - 1),
+            (center.0, center.1 + 1),
+        ] {
+            paint(&mut build_data.map, 1, x, y);
+        }
+        build_data
+            .reserve_starting_position(center)
+            .expect("The center has just been turned into floor!");
+        build_data.take_snapshot();
+
+        let interior = (width.saturating_sub(2) * height.saturating_sub(2)) as f32;
+        let desired_floor = (self.floor_percent * interior) as usize;
+        let mut floor = build_data.map.count_floor();
+
+        let mut particles = 0;
+        while floor < desired_floor {
+            if particles >= MAX_PARTICLES {
+                warn!(
+                    "Stopping after {particles} particles without reaching the desired floor area!"
+                );
+                break;
+            }
+            let (x, y) = match self.mode {
+                DlaMode::WalkInwards => self.walk_inwards(rng, &build_data.map),
+                DlaMode::WalkOutwards => self.walk_outwards(rng, &build_data.map),
+                DlaMode::CentralAttractor => self.central_attractor(rng, &build_data.map),
+            };
+            paint(&mut build_data.map, self.brush_size, x, y);
+            particles += 1;
+            if particles % SNAPSHOT_INTERVAL == 0 {
+                build_data.take_snapshot();
+            }
+            floor = build_data.map.count_floor();
+        }
+        build_data.take_snapshot();
+    }
+}
+
+fn is_wall(map: &GameMap, (x, y): (u32, u32)) -> bool {
+    map.tiles[map.xy_to_idx(x, y).unwrap()] == TileType::Wall
+}
+
+fn random_interior_point(rng: &mut MapRng, map: &GameMap) -> (u32, u32) {
+    (
+        rng.gen_range(1..map.width - 1),
+        rng.gen_range(1..map.height - 1),
+    )
+}
+
+/// Takes a random orthogonal step that does not leave the interior of the map
+fn stumble(rng: &mut MapRng, map: &GameMap, (x, y): (u32, u32)) -> (u32, u32) {
+    match rng.gen_range(0..4) {
+        0 if x > 1 => (x - 1, y),
+        1 if x < map.width - 2 => (x + 1, y),
+        2 if y > 1 => (x, y - 1),
+        3 if y < map.height - 2 => (x, y + 1),
+        _ => (x, y),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_every_mode_reaches_floor_percent() {
+        let (width, height) = (40, 30);
+        let desired_floor = (0.25 * ((width - 2) * (height - 2)) as f32) as usize;
+        for mode in [
+            DlaMode::WalkInwards,
+            DlaMode::WalkOutwards,
+            DlaMode::CentralAttractor,
+        ] {
+            for seed in 0..5 {
+                let mut rng = MapRng::seed_from_u64(seed);
+                let mut build_data = MapBuildData::new(width, height);
+                DlaBuilder::new(mode, 0.25, 1).build_map(&mut rng, &mut build_data);
+
+                let map = &build_data.map;
+                assert!(map.count_floor() >= desired_floor, "{mode:?}");
+                assert_eq!(
+                    build_data.metadata.starting_position,
+                    Some((width / 2, height / 2))
+                );
+                // Particles never stick to the map boundary
+                for x in 0..width {
+                    for y in [0, height - 1] {
+                        assert!(is_wall(map, (x, y)), "{mode:?}");
+                    }
+                }
+                for y in 0..height {
+                    for x in [0, width - 1] {
+                        assert!(is_wall(map, (x, y)), "{mode:?}");
+                    }
+                }
+            }
+        }
+    }
+}
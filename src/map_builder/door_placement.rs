@@ -0,0 +1,145 @@
+use rand::Rng;
+
+use super::{spawner::Spawnables, MapBuildData, MapModifier, MapRng};
+
+/// Places doors where corridors enter the [`MapMetadata::rooms`](super::MapMetadata::rooms), i.e. on
+/// floor tiles next to a room's outline that are flanked by walls on both sides.
+pub struct DoorPlacement {
+    /// Probability of placing a door at each possible position
+    door_chance: f32,
+}
+
+impl DoorPlacement {
+    pub fn new(door_chance: f32) -> Box<DoorPlacement> {
+        Box::new(DoorPlacement { door_chance })
+    }
+
+    /// Checks if a door fits onto the given tile
+    fn is_door_position(build_data: &MapBuildData, (x, y): (u32, u32)) -> bool {
+        let map = &build_data.map;
+        let is_walkable = |x: u32, y: u32| {
+            map.xy_to_idx(x, y)
+                .is_ok_and(|idx| map.tiles[idx].is_walkable())
+        };
+        // Never cover anything that has been placed before
+        if x == 0 || y == 0 || !build_data.is_free((x, y)) {
+            return false;
+        }
+        let spawn_list = &build_data.metadata.spawn_list;
+        // Neighboring doors would block each other from being opened
+        let next_to_door = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .iter()
+            .any(|pos| matches!(spawn_list.get(pos), Some(Spawnables::Door)));
+        if next_to_door {
+            return false;
+        }
+        let horizontal_passage = !is_walkable(x, y - 1) && !is_walkable(x, y + 1);
+        let vertical_passage = !is_walkable(x - 1, y) && !is_walkable(x + 1, y);
+        horizontal_passage != vertical_passage
+    }
+}
+
+impl MapModifier for DoorPlacement {
+    fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let rooms = build_data
+            .metadata
+            .rooms
+            .as_ref()
+            .expect("Need rooms to place doors at!");
+
+        let mut candidates = Vec::new();
+        for room in rooms.iter() {
+            // The outline consists of all tiles next to the room that are not part of any room.
+            // Rooms never touch the map's edge, so the coordinates cannot underflow.
+            let outline = room
+                .tiles()
+                .into_iter()
+                .flat_map(|(x, y)| [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]);
+            for pos in outline {
+                let inside_room = rooms.iter().any(|r| r.contains(pos.0, pos.1));
+                if !inside_room && !candidates.contains(&pos) {
+                    candidates.push(pos);
+                }
+            }
+        }
+
+        for pos in candidates {
+            if Self::is_door_position(build_data, pos) && rng.gen::<f32>() < self.door_chance {
+                build_data
+                    .reserve_spawn(pos, Spawnables::Door)
+                    .expect("Doors are only placed on free tiles!");
+            }
+        }
+        build_data.take_snapshot();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map_builder::{
+        rect::Rect,
+        room::{Room, RoomShape},
+        simple_map_builder::{apply_room_to_map, apply_tunnel},
+    };
+
+    /// Builds a map with three rooms, one corridor leading east and one leading south from the first room
+    fn build_data() -> MapBuildData {
+        let mut build_data = MapBuildData::new(20, 16);
+        let rooms = vec![
+            Room::new(RoomShape::Rectangle, Rect::new(1, 1, 5, 5)),
+            Room::new(RoomShape::Rectangle, Rect::new(12, 1, 5, 5)),
+            Room::new(RoomShape::Rectangle, Rect::new(1, 10, 5, 5)),
+        ];
+        for room in rooms.iter() {
+            apply_room_to_map(&mut build_data.map, room);
+        }
+        apply_tunnel(&mut build_data.map, 3, 3, 14, 3, true);
+        apply_tunnel(&mut build_data.map, 3, 3, 3, 12, true);
+        build_data.metadata.rooms = Some(rooms);
+        build_data
+    }
+
+    fn doors(build_data: &MapBuildData) -> Vec<(u32, u32)> {
+        let mut doors: Vec<(u32, u32)> = build_data
+            .metadata
+            .spawn_list
+            .iter()
+            .filter(|(_, &s)| s == Spawnables::Door)
+            .map(|(&pos, _)| pos)
+            .collect();
+        doors.sort();
+        doors
+    }
+
+    #[test]
+    fn test_doors_are_placed_where_corridors_enter_rooms() {
+        let mut rng = MapRng::seed_from_u64(0);
+
+        let mut all_doors = build_data();
+        DoorPlacement::new(1.0).modify_map(&mut rng, &mut all_doors);
+        assert_eq!(doors(&all_doors), [(3, 6), (3, 10), (6, 3), (12, 3)]);
+
+        let mut no_doors = build_data();
+        DoorPlacement::new(0.0).modify_map(&mut rng, &mut no_doors);
+        assert!(doors(&no_doors).is_empty());
+
+        // Reserved tiles are never covered
+        let mut reserved = build_data();
+        reserved.reserve_starting_position((6, 3)).unwrap();
+        DoorPlacement::new(1.0).modify_map(&mut rng, &mut reserved);
+        assert_eq!(doors(&reserved), [(3, 6), (3, 10), (12, 3)]);
+    }
+
+    #[test]
+    fn test_no_doors_in_open_room_edges() {
+        let mut rng = MapRng::seed_from_u64(0);
+        let mut build_data = build_data();
+        // Widen the eastern corridor next to the first room so that the entrance is not flanked by walls
+        apply_tunnel(&mut build_data.map, 6, 4, 8, 4, true);
+        DoorPlacement::new(1.0).modify_map(&mut rng, &mut build_data);
+        assert_eq!(doors(&build_data), [(3, 6), (3, 10), (12, 3)]);
+    }
+}
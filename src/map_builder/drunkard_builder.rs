@@ -0,0 +1,129 @@
+use bevy::log::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use super::{brush::paint, InitialMapBuilder, MapBuildData, MapRng};
+
+/// Upper limit for the number of walkers to avoid looping forever if the desired floor area cannot be reached
+const MAX_WALKERS: u32 = 10_000;
+
+/// Determines where each new walker starts digging
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum DrunkSpawnMode {
+    /// All walkers start at the center of the map
+    StartingPoint,
+    /// The first walker starts at the center, all others at random points of the map
+    Random,
+}
+
+/// Carves out caves by letting 'drunken' walkers stumble randomly across the map until a target
+/// fraction of the map has been turned into floor tiles. Sets the map center as the starting position.
+pub struct DrunkardsWalkBuilder {
+    spawn_mode: DrunkSpawnMode,
+    /// Number of steps each walker takes before it stops digging
+    lifetime: u32,
+    /// Fraction of the map's interior that should become floor tiles
+    floor_percent: f32,
+    brush_size: u32,
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(
+        spawn_mode: DrunkSpawnMode,
+        lifetime: u32,
+        floor_percent: f32,
+        brush_size: u32,
+    ) -> Box<DrunkardsWalkBuilder> {
+        Box::new(DrunkardsWalkBuilder {
+            spawn_mode,
+            lifetime,
+            floor_percent,
+            brush_size,
+        })
+    }
+}
+
+impl InitialMapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let width = build_data.map.width;
+        let height = build_data.map.height;
+        let center = (width / 2, height / 2);
+        paint(&mut build_data.map, self.brush_size, center.0, center.1);
+        build_data
+            .reserve_starting_position(center)
+            .expect("The center has just been turned into floor!");
+        build_data.take_snapshot();
+
+        let interior = (width.saturating_sub(2) * height.saturating_sub(2)) as f32;
+        let desired_floor = (self.floor_percent * interior) as usize;
+
+        let mut walkers = 0;
+        while build_data.map.count_floor() < desired_floor {
+            if walkers >= MAX_WALKERS {
+                warn!("Stopping after {walkers} walkers without reaching the desired floor area!");
+                break;
+            }
+            let (mut x, mut y) = match self.spawn_mode {
+                DrunkSpawnMode::StartingPoint => center,
+                DrunkSpawnMode::Random if walkers == 0 => center,
+                DrunkSpawnMode::Random => {
+                    (rng.gen_range(1..width - 1), rng.gen_range(1..height - 1))
+                }
+            };
+
+            for _ in 0..self.lifetime {
+                paint(&mut build_data.map, self.brush_size, x, y);
+                // Stumble into a random direction but stay inside the map boundary
+                match rng.gen_range(0..4) {
+                    0 if x > 1 => x -= 1,
+                    1 if x < width - 2 => x += 1,
+                    2 if y > 1 => y -= 1,
+                    3 if y < height - 2 => y += 1,
+                    _ => {}
+                }
+            }
+            walkers += 1;
+            build_data.take_snapshot();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map::TileType;
+
+    #[test]
+    fn test_every_spawn_mode_reaches_floor_percent() {
+        let (width, height) = (40, 30);
+        let desired_floor = (0.4 * ((width - 2) * (height - 2)) as f32) as usize;
+        for spawn_mode in [DrunkSpawnMode::StartingPoint, DrunkSpawnMode::Random] {
+            for seed in 0..5 {
+                let mut rng = MapRng::seed_from_u64(seed);
+                let mut build_data = MapBuildData::new(width, height);
+                DrunkardsWalkBuilder::new(spawn_mode, 200, 0.4, 1)
+                    .build_map(&mut rng, &mut build_data);
+
+                let map = &build_data.map;
+                assert!(map.count_floor() >= desired_floor, "{spawn_mode:?}");
+                assert_eq!(
+                    build_data.metadata.starting_position,
+                    Some((width / 2, height / 2))
+                );
+                // Walkers never dig through the map boundary
+                for x in 0..width {
+                    for y in [0, height - 1] {
+                        assert_eq!(map.tiles[map.xy_to_idx(x, y).unwrap()], TileType::Wall);
+                    }
+                }
+                for y in 0..height {
+                    for x in [0, width - 1] {
+                        assert_eq!(map.tiles[map.xy_to_idx(x, y).unwrap()], TileType::Wall);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use rand::Rng;
+use bevy::log::*;
+use rand::seq::SliceRandom;
 
 use super::{spawner::Spawnables, MapBuildData, MapModifier, MapRng};
-use crate::map::TileType;
 
+/// Places the objective on any free tile of the map. Unlike the
+/// [`DistantObjectiveSpawner`](super::distant_objective_spawner::DistantObjectiveSpawner), this does
+/// not need a starting position but may place the objective right next to the player.
 pub struct GeneralObjectiveSpawner {
     objective: Spawnables,
 }
@@ -15,34 +18,47 @@ impl GeneralObjectiveSpawner {
 
 impl MapModifier for GeneralObjectiveSpawner {
     fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
-        // FIXME: This may select an already occupied spawn position
-        let random_idx = rng.gen_range(0..build_data.map.length());
-        // Try to find a floor tile after the randomly selected index
-        if let Some((objective_idx, _)) = build_data
-            .map
-            .tiles
-            .iter()
-            .enumerate()
-            .skip(random_idx)
-            .find(|&(_, t)| *t == TileType::Floor)
-        {
-            let pos = build_data.map.idx_to_xy(objective_idx).unwrap();
-            build_data.metadata.spawn_list.insert(pos, self.objective);
-        } else if let Some((objective_idx, _)) = build_data
-            // If that fails, search backwards from the index
-            .map
-            .tiles
-            .iter()
-            .enumerate()
-            .take(random_idx)
-            .rev()
-            .find(|&(_, t)| *t == TileType::Floor)
-        {
-            let pos = build_data.map.idx_to_xy(objective_idx).unwrap();
-            build_data.metadata.spawn_list.insert(pos, self.objective);
-        } else {
-            panic!("Cannot find a single floor tile as the starting position!");
-        }
+        let Some(&pos) = build_data.free_tiles().choose(rng) else {
+            warn!("Cannot find a single free tile to place the objective on!");
+            return;
+        };
+        build_data
+            .reserve_spawn(pos, self.objective)
+            .expect("The objective is only placed on free tiles!");
         build_data.take_snapshot();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map::TileType;
+
+    #[test]
+    fn test_objective_is_placed_on_a_free_tile() {
+        let mut build_data = MapBuildData::new(5, 3);
+        for x in 1..=3 {
+            let idx = build_data.map.xy_to_idx(x, 1).unwrap();
+            build_data.map.tiles[idx] = TileType::Floor;
+        }
+        build_data.reserve_starting_position((1, 1)).unwrap();
+        build_data
+            .reserve_spawn((2, 1), Spawnables::Turtle)
+            .unwrap();
+
+        let mut rng = MapRng::seed_from_u64(0);
+        GeneralObjectiveSpawner::new(Spawnables::TreasureChest)
+            .modify_map(&mut rng, &mut build_data);
+        assert_eq!(
+            build_data.metadata.spawn_list.get(&(3, 1)),
+            Some(&Spawnables::TreasureChest)
+        );
+
+        // Nothing left to place another objective on
+        GeneralObjectiveSpawner::new(Spawnables::TreasureChest)
+            .modify_map(&mut rng, &mut build_data);
+        assert_eq!(build_data.metadata.spawn_list.len(), 2);
+    }
+}
@@ -0,0 +1,252 @@
+use rand::{seq::SliceRandom, Rng};
+
+use super::{
+    rect::Rect,
+    room::{Room, RoomShape},
+    simple_map_builder::apply_room_to_map,
+    InitialMapBuilder, MapBuildData, MapRng,
+};
+use crate::map::{GameMap, TileType};
+
+/// Number of carved maze cells between two snapshots
+const SNAPSHOT_INTERVAL: usize = 50;
+
+/// Carves a perfect maze with a recursive backtracker, optionally removes some of its dead ends
+/// ('braiding'), and carves a few rooms into it.
+///
+/// Maze cells are placed on odd coordinates, the walls between them on even coordinates. Rooms are
+/// aligned to the same grid and recorded in [`MapMetadata::rooms`](super::MapMetadata::rooms) if any are requested.
+pub struct MazeBuilder {
+    /// Fraction of dead ends to remove
+    braid: f32,
+    /// Maximum number of rooms to carve into the maze, no rooms are recorded if this is zero
+    num_rooms: u32,
+    min_room_size: u32,
+    max_room_size: u32,
+}
+
+impl MazeBuilder {
+    pub fn new(
+        braid: f32,
+        num_rooms: u32,
+        min_room_size: u32,
+        max_room_size: u32,
+    ) -> Box<MazeBuilder> {
+        Box::new(MazeBuilder {
+            braid,
+            num_rooms,
+            min_room_size,
+            max_room_size,
+        })
+    }
+
+    /// Runs a randomized depth-first search over all maze cells, carving the walls between visited cells
+    fn carve_maze(&self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let cols = (build_data.map.width - 1) / 2;
+        let rows = (build_data.map.height - 1) / 2;
+        if cols == 0 || rows == 0 {
+            return;
+        }
+        let cell_to_xy = |(cx, cy): (u32, u32)| (2 * cx + 1, 2 * cy + 1);
+
+        let mut visited = vec![false; (cols * rows) as usize];
+        let start = (rng.gen_range(0..cols), rng.gen_range(0..rows));
+        visited[(start.1 * cols + start.0) as usize] = true;
+        set_floor(&mut build_data.map, cell_to_xy(start));
+        let mut stack = vec![start];
+        let mut carved = 1;
+
+        while let Some(&(cx, cy)) = stack.last() {
+            let mut unvisited = Vec::with_capacity(4);
+            if cx > 0 {
+                unvisited.push((cx - 1, cy));
+            }
+            if cx + 1 < cols {
+                unvisited.push((cx + 1, cy));
+            }
+            if cy > 0 {
+                unvisited.push((cx, cy - 1));
+            }
+            if cy + 1 < rows {
+                unvisited.push((cx, cy + 1));
+            }
+            unvisited.retain(|&(nx, ny)| !visited[(ny * cols + nx) as usize]);
+
+            if let Some(&next) = unvisited.choose(rng) {
+                visited[(next.1 * cols + next.0) as usize] = true;
+                let (x, y) = cell_to_xy((cx, cy));
+                let (nx, ny) = cell_to_xy(next);
+                // Carve the wall between both cells as well as the next cell
+                set_floor(&mut build_data.map, ((x + nx) / 2, (y + ny) / 2));
+                set_floor(&mut build_data.map, (nx, ny));
+                stack.push(next);
+
+                carved += 1;
+                if carved % SNAPSHOT_INTERVAL == 0 {
+                    build_data.take_snapshot();
+                }
+            } else {
+                stack.pop();
+            }
+        }
+        build_data.take_snapshot();
+    }
+
+    /// Removes a fraction of all dead ends by knocking down one of their walls
+    fn braid(&self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let map = &build_data.map;
+        let mut dead_ends: Vec<(u32, u32)> = (1..map.height - 1)
+            .step_by(2)
+            .flat_map(|y| (1..map.width - 1).step_by(2).map(move |x| (x, y)))
+            .filter(|&pos| is_dead_end(map, pos))
+            .collect();
+        dead_ends.shuffle(rng);
+        let num_removals = (self.braid * dead_ends.len() as f32).round() as usize;
+
+        for (x, y) in dead_ends.into_iter().take(num_removals) {
+            let map = &mut build_data.map;
+            // Previous removals may have already connected this dead end
+            if !is_dead_end(map, (x, y)) {
+                continue;
+            }
+            let mut walls = Vec::with_capacity(3);
+            if x > 2 {
+                walls.push((x - 1, y));
+            }
+            if x + 2 < map.width - 1 {
+                walls.push((x + 1, y));
+            }
+            if y > 2 {
+                walls.push((x, y - 1));
+            }
+            if y + 2 < map.height - 1 {
+                walls.push((x, y + 1));
+            }
+            walls.retain(|&pos| !is_floor(map, pos));
+            if let Some(&wall) = walls.choose(rng) {
+                set_floor(map, wall);
+            }
+        }
+        build_data.take_snapshot();
+    }
+
+    /// Carves non-overlapping rooms aligned to the maze grid
+    fn carve_rooms(&self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let mut rooms: Vec<Room> = Vec::new();
+        let (width, height) = (build_data.map.width, build_data.map.height);
+        let max_tries = self.num_rooms * 10;
+
+        for _ in 0..max_tries {
+            if rooms.len() as u32 >= self.num_rooms {
+                break;
+            }
+            // Even sizes and positions keep the room walls on the same grid as the maze walls
+            let w = round_up_to_even(rng.gen_range(self.min_room_size..=self.max_room_size));
+            let h = round_up_to_even(rng.gen_range(self.min_room_size..=self.max_room_size));
+            if w + 2 > width || h + 2 > height {
+                continue;
+            }
+            let x = 2 * rng.gen_range(0..=(width - 1 - w) / 2);
+            let y = 2 * rng.gen_range(0..=(height - 1 - h) / 2);
+            let new_room = Room::new(RoomShape::Rectangle, Rect::new(x, y, w, h));
+            if !rooms.iter().any(|r| new_room.intersect(r)) {
+                apply_room_to_map(&mut build_data.map, &new_room);
+                rooms.push(new_room);
+                build_data.take_snapshot();
+            }
+        }
+        build_data.metadata.rooms = Some(rooms);
+    }
+}
+
+impl InitialMapBuilder for MazeBuilder {
+    fn build_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        self.carve_maze(rng, build_data);
+        if self.braid > 0.0 {
+            self.braid(rng, build_data);
+        }
+        if self.num_rooms > 0 {
+            self.carve_rooms(rng, build_data);
+        }
+    }
+}
+
+fn round_up_to_even(n: u32) -> u32 {
+    n + n % 2
+}
+
+fn set_floor(map: &mut GameMap, (x, y): (u32, u32)) {
+    if let Ok(idx) = map.xy_to_idx(x, y) {
+        map.tiles[idx] = TileType::Floor;
+    }
+}
+
+fn is_floor(map: &GameMap, (x, y): (u32, u32)) -> bool {
+    map.xy_to_idx(x, y)
+        .is_ok_and(|idx| map.tiles[idx] == TileType::Floor)
+}
+
+/// Returns true if the given tile is a floor tile with exactly one orthogonal floor neighbor
+fn is_dead_end(map: &GameMap, (x, y): (u32, u32)) -> bool {
+    is_floor(map, (x, y))
+        && [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .into_iter()
+            .filter(|&pos| is_floor(map, pos))
+            .count()
+            == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn count_dead_ends(map: &GameMap) -> usize {
+        (1..map.height - 1)
+            .flat_map(|y| (1..map.width - 1).map(move |x| (x, y)))
+            .filter(|&pos| is_dead_end(map, pos))
+            .count()
+    }
+
+    #[test]
+    fn test_braid_removes_dead_ends() {
+        for seed in 0..5 {
+            let mut perfect = MapBuildData::new(31, 21);
+            MazeBuilder::new(0.0, 0, 0, 0)
+                .build_map(&mut MapRng::seed_from_u64(seed), &mut perfect);
+            let mut braided = MapBuildData::new(31, 21);
+            MazeBuilder::new(1.0, 0, 0, 0)
+                .build_map(&mut MapRng::seed_from_u64(seed), &mut braided);
+
+            assert!(count_dead_ends(&perfect.map) > 0);
+            assert_eq!(count_dead_ends(&braided.map), 0);
+            assert!(braided.map.count_floor() > perfect.map.count_floor());
+        }
+    }
+
+    #[test]
+    fn test_rooms_are_only_recorded_when_they_fit() {
+        let mut rng = MapRng::seed_from_u64(0);
+
+        let mut no_rooms = MapBuildData::new(31, 21);
+        MazeBuilder::new(0.0, 0, 3, 5).build_map(&mut rng, &mut no_rooms);
+        assert!(no_rooms.metadata.rooms.is_none());
+
+        let mut too_large = MapBuildData::new(31, 21);
+        MazeBuilder::new(0.0, 3, 30, 40).build_map(&mut rng, &mut too_large);
+        assert!(too_large.metadata.rooms.unwrap().is_empty());
+
+        let mut fitting = MapBuildData::new(31, 21);
+        MazeBuilder::new(0.0, 3, 3, 5).build_map(&mut rng, &mut fitting);
+        let rooms = fitting.metadata.rooms.unwrap();
+        assert!(!rooms.is_empty() && rooms.len() <= 3);
+        for (i, room) in rooms.iter().enumerate() {
+            assert!(rooms[i + 1..].iter().all(|other| !room.intersect(other)));
+            for (x, y) in room.tiles() {
+                assert!(x > 0 && x < 30 && y > 0 && y < 20);
+                assert!(is_floor(&fitting.map, (x, y)));
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use bevy::log::*;
 use std::collections::HashMap;
 
 use crate::map::GameMap;
@@ -5,41 +6,167 @@ use crate::map::GameMap;
 pub type MapRng = rand::rngs::StdRng;
 
 pub mod arbitrary_starting_point;
+mod brush;
+pub mod bsp_builder;
 pub mod cellular_builder;
+pub mod chain_config;
+pub mod corridors;
 pub mod cull_unreachable;
+pub mod distant_objective_spawner;
+pub mod dla_builder;
+pub mod door_placement;
+pub mod drunkard_builder;
 pub mod general_objective_spawner;
-mod random_table;
+pub mod maze_builder;
+mod noise;
+pub mod noise_builder;
+pub mod prefab_vaults;
+pub mod random_table;
 pub mod rect;
+pub mod region;
 pub mod region_based_builders;
+pub mod room;
 pub mod room_based_builders;
 pub mod simple_map_builder;
 pub mod spawner;
+pub mod symmetry;
+pub mod validation;
+pub mod voronoi_builder;
+pub mod wfc_builder;
 
 /// Combines abstract map properties, the concrete tile layout, and potentially a history of snapshots
 pub struct MapBuildData {
     map: GameMap,
     metadata: MapMetadata,
-    pub history: Vec<(GameMap, MapMetadata)>,
+    pub history: MapHistory,
+    /// Depth of the level the map is built for, starting with 1 for the first level
+    pub depth: u32,
 }
 
-/// Represents a region of related tiles, e.g. to spawn monsters together in their lair
-/// Does not have to be a particular geometric shape (unlike a 'room' which for now is
-/// limited to be a [`Rect`](rect::Rect))
-/// Uses a simple type alias for now
-pub type Region = Vec<(u32, u32)>;
+/// Snapshots of the map and its metadata after each building step, oldest first
+pub type MapHistory = Vec<(GameMap, MapMetadata)>;
 
 /// Contains abstract properties of a map that may determine the concrete tile layout and their contents
 #[derive(Debug, Clone, Default)]
 pub struct MapMetadata {
     pub starting_position: Option<(u32, u32)>,
-    pub rooms: Option<Vec<rect::Rect>>,
-    pub regions: Option<Vec<Region>>,
+    pub rooms: Option<Vec<room::Room>>,
+    /// Pairs of indices into [`Self::rooms`] that the initial builder suggests to connect,
+    /// e.g. rooms in sibling partitions of a [`BspRoomBuilder`](bsp_builder::BspRoomBuilder)
+    pub room_links: Option<Vec<(usize, usize)>>,
+    pub regions: Option<Vec<region::Region>>,
     pub spawn_list: SpawnList,
+    /// Sizes of the unreachable areas removed by [`CullUnreachable`](cull_unreachable::CullUnreachable)
+    pub culled_components: Vec<usize>,
 }
 
 pub type SpawnList = HashMap<(u32, u32), spawner::Spawnables>;
 
+/// Returned when trying to reserve a tile that is not walkable or already taken by the player start or a spawn
+#[derive(Debug, PartialEq)]
+pub struct OccupiedError;
+
+/// Returned when a spawn cannot be added to the [`SpawnList`]
+#[derive(Debug, PartialEq)]
+pub enum SpawnError {
+    /// The tile is not walkable or already taken by the player start or a spawn
+    Occupied,
+    /// The map already holds as many of the spawn as [`Spawnables::max_per_map`](spawner::Spawnables::max_per_map) allows
+    LimitReached,
+    /// The spawn is [immovable](spawner::Spawnables::is_immovable) and could cut off parts of the map here
+    Chokepoint,
+}
+
 impl MapBuildData {
+    /// Creates build data for an empty map (only walls) of the given size without any history
+    pub fn new(width: u32, height: u32) -> Self {
+        MapBuildData {
+            map: GameMap::new(width, height),
+            metadata: MapMetadata::default(),
+            history: Vec::new(),
+            depth: 1,
+        }
+    }
+
+    /// Checks if the position has been reserved for the player start or a spawn.
+    /// All modifiers placing something onto the map should only use free positions, see [`Self::is_free`].
+    pub fn is_reserved(&self, pos: (u32, u32)) -> bool {
+        self.metadata.starting_position == Some(pos) || self.metadata.spawn_list.contains_key(&pos)
+    }
+
+    /// Iterates over all positions reserved for the player start or a spawn
+    pub fn reserved_positions(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.metadata
+            .starting_position
+            .into_iter()
+            .chain(self.metadata.spawn_list.keys().copied())
+    }
+
+    /// Checks if the position is a walkable tile that has not been reserved yet
+    pub fn is_free(&self, pos: (u32, u32)) -> bool {
+        let (x, y) = pos;
+        self.map
+            .xy_to_idx(x, y)
+            .is_ok_and(|idx| self.map.tiles[idx].is_walkable())
+            && !self.is_reserved(pos)
+    }
+
+    /// Returns all free positions (see [`Self::is_free`]) in index order
+    pub fn free_tiles(&self) -> Vec<(u32, u32)> {
+        (0..self.map.length())
+            .map(|idx| self.map.idx_to_xy(idx).unwrap())
+            .filter(|&pos| self.is_free(pos))
+            .collect()
+    }
+
+    /// Moves the player's starting position to a free tile, releasing the previous one
+    pub fn reserve_starting_position(&mut self, pos: (u32, u32)) -> Result<(), OccupiedError> {
+        if self.metadata.starting_position != Some(pos) && !self.is_free(pos) {
+            return Err(OccupiedError);
+        }
+        self.metadata.starting_position = Some(pos);
+        Ok(())
+    }
+
+    /// Checks if all eight neighbors of the position are walkable. Blocking such a position never
+    /// disconnects its neighbors, as they can still walk around it.
+    pub fn is_open_space(&self, (x, y): (u32, u32)) -> bool {
+        // Wrapped coordinates lie outside the map
+        let (left, down) = (x.wrapping_sub(1), y.wrapping_sub(1));
+        [left, x, x + 1]
+            .into_iter()
+            .flat_map(|nx| [down, y, y + 1].map(|ny| (nx, ny)))
+            .filter(|&neighbor| neighbor != (x, y))
+            .all(|(nx, ny)| {
+                self.map
+                    .xy_to_idx(nx, ny)
+                    .is_ok_and(|idx| self.map.tiles[idx].is_walkable())
+            })
+    }
+
+    /// Adds a spawn to the [`SpawnList`] if the position is free and the spawn's cap has not been
+    /// reached yet, never replacing a previous spawn. Immovable spawns are only placed in open space.
+    pub fn reserve_spawn(
+        &mut self,
+        pos: (u32, u32),
+        spawn: spawner::Spawnables,
+    ) -> Result<(), SpawnError> {
+        if !self.is_free(pos) {
+            return Err(SpawnError::Occupied);
+        }
+        if spawn.is_immovable() && !self.is_open_space(pos) {
+            return Err(SpawnError::Chokepoint);
+        }
+        if let Some(max) = spawn.max_per_map() {
+            let placed = self.metadata.spawn_list.values().filter(|&&s| s == spawn);
+            if placed.count() as u32 >= max {
+                return Err(SpawnError::LimitReached);
+            }
+        }
+        self.metadata.spawn_list.insert(pos, spawn);
+        Ok(())
+    }
+
     /// Adds a snapshot of the current map state to the history
     pub fn take_snapshot(&mut self) {
         let snapshot = self.map.clone();
@@ -70,49 +197,184 @@ impl InitialMapBuilderTrait for HasInitial {}
 pub struct BuilderChain<Initialized: InitialMapBuilderTrait> {
     initial: Initialized,
     modifiers: Vec<Box<dyn MapModifier>>,
-    build_data: MapBuildData,
+    width: u32,
+    height: u32,
+    depth: u32,
+    requirements: validation::MapRequirements,
 }
 
-const WIDTH: u32 = 80;
-const HEIGHT: u32 = 53;
+/// Smallest supported width and height of a map. Builders may still fail to place anything useful
+/// onto maps this small, but they never panic due to the map's size.
+pub const MIN_MAP_SIZE: u32 = 16;
 
 impl BuilderChain<Uninitialized> {
-    pub fn new() -> BuilderChain<Uninitialized> {
+    /// Creates a chain for building a map of the given size, which is increased to at least [`MIN_MAP_SIZE`]
+    pub fn new(width: u32, height: u32) -> BuilderChain<Uninitialized> {
+        if width < MIN_MAP_SIZE || height < MIN_MAP_SIZE {
+            warn!(
+                "Increasing the map size of {width} x {height} to the minimum of {MIN_MAP_SIZE}!"
+            );
+        }
         BuilderChain {
             initial: Uninitialized,
             modifiers: Vec::new(),
-            build_data: MapBuildData {
-                map: GameMap::new(WIDTH, HEIGHT),
-                metadata: MapMetadata::default(),
-                history: Vec::new(),
-            },
+            width: width.max(MIN_MAP_SIZE),
+            height: height.max(MIN_MAP_SIZE),
+            depth: 1,
+            requirements: validation::MapRequirements::default(),
         }
     }
 
+    /// Sets the (1-based) depth of the level the map is built for, see [`MapBuildData::depth`]
+    pub fn at_depth(mut self, depth: u32) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
     pub fn start_with(self, initial: Box<dyn InitialMapBuilder>) -> BuilderChain<HasInitial> {
         BuilderChain {
             initial: HasInitial { builder: initial },
             modifiers: self.modifiers,
-            build_data: self.build_data,
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+            requirements: self.requirements,
         }
     }
 }
 
 impl<I: InitialMapBuilderTrait> BuilderChain<I> {
+    /// Returns the width and height of the maps built by this chain
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
     pub fn with(&mut self, builder: Box<dyn MapModifier>) -> &mut Self {
         self.modifiers.push(builder);
         self
     }
+
+    /// Replaces the default [`MapRequirements`](validation::MapRequirements) of the finished map
+    pub fn require(&mut self, requirements: validation::MapRequirements) -> &mut Self {
+        self.requirements = requirements;
+        self
+    }
 }
 
+/// Number of maps a [`BuilderChain`] builds at most to find one meeting its requirements
+pub const MAX_BUILD_ATTEMPTS: u32 = 10;
+
 impl BuilderChain<HasInitial> {
-    pub fn build_map(mut self, rng: &mut MapRng) -> (GameMap, MapMetadata) {
-        self.initial.builder.build_map(rng, &mut self.build_data);
+    /// Runs all builders of the chain and returns the final map, its metadata, and the [`MapHistory`].
+    /// Maps failing the chain's requirements are rebuilt with the same RNG (up to [`MAX_BUILD_ATTEMPTS`]
+    /// times), so the result only depends on the RNG's seed. Returns the requirement the last attempt
+    /// did not meet if all attempts fail.
+    pub fn build_map(
+        mut self,
+        rng: &mut MapRng,
+    ) -> Result<(GameMap, MapMetadata, MapHistory), validation::InvalidMapError> {
+        let mut attempt = 1;
+        loop {
+            let mut build_data = MapBuildData::new(self.width, self.height);
+            build_data.depth = self.depth;
+            self.initial.builder.build_map(rng, &mut build_data);
+            for builder in self.modifiers.iter_mut() {
+                builder.modify_map(rng, &mut build_data);
+            }
+
+            match self.requirements.validate(&build_data) {
+                Err(e) if attempt < MAX_BUILD_ATTEMPTS => {
+                    warn!("Rebuilding invalid map (attempt {attempt}): {e}");
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+                Ok(()) => return Ok((build_data.map, build_data.metadata, build_data.history)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+    use spawner::Spawnables;
+
+    #[test]
+    fn test_reservations() {
+        let mut build_data = MapBuildData::new(5, 3);
+        for x in 1..4 {
+            let idx = build_data.map.xy_to_idx(x, 1).unwrap();
+            build_data.map.tiles[idx] = TileType::Floor;
+        }
+        assert_eq!(build_data.free_tiles(), [(1, 1), (2, 1), (3, 1)]);
+
+        assert_eq!(
+            build_data.reserve_starting_position((0, 0)),
+            Err(OccupiedError)
+        );
+        assert_eq!(build_data.reserve_starting_position((1, 1)), Ok(()));
+        assert_eq!(
+            build_data.reserve_spawn((1, 1), Spawnables::Turtle),
+            Err(SpawnError::Occupied)
+        );
+        assert_eq!(build_data.reserve_spawn((2, 1), Spawnables::Turtle), Ok(()));
+        assert_eq!(
+            build_data.reserve_spawn((2, 1), Spawnables::TreasureChest),
+            Err(SpawnError::Occupied)
+        );
+        assert!(matches!(
+            build_data.metadata.spawn_list.get(&(2, 1)),
+            Some(Spawnables::Turtle)
+        ));
+        assert_eq!(
+            build_data.reserve_starting_position((2, 1)),
+            Err(OccupiedError)
+        );
+        assert_eq!(build_data.free_tiles(), [(3, 1)]);
 
-        for builder in self.modifiers.iter_mut() {
-            builder.modify_map(rng, &mut self.build_data);
+        // Moving the start releases the previous position
+        assert_eq!(build_data.reserve_starting_position((3, 1)), Ok(()));
+        assert_eq!(build_data.free_tiles(), [(1, 1)]);
+
+        // Platino stays out of the way and is unique
+        let mut build_data = MapBuildData::new(7, 5);
+        for (x, y) in (1..6).flat_map(|x| (1..4).map(move |y| (x, y))) {
+            let idx = build_data.map.xy_to_idx(x, y).unwrap();
+            build_data.map.tiles[idx] = TileType::Floor;
         }
+        assert_eq!(
+            build_data.reserve_spawn((1, 2), Spawnables::Platino),
+            Err(SpawnError::Chokepoint)
+        );
+        assert_eq!(
+            build_data.reserve_spawn((2, 2), Spawnables::Platino),
+            Ok(())
+        );
+        assert_eq!(
+            build_data.reserve_spawn((4, 2), Spawnables::Platino),
+            Err(SpawnError::LimitReached)
+        );
+    }
+
+    /// Leaves the map filled with walls
+    struct NoopBuilder;
+
+    impl InitialMapBuilder for NoopBuilder {
+        fn build_map(&mut self, _rng: &mut MapRng, _build_data: &mut MapBuildData) {}
+    }
+
+    #[test]
+    fn test_build_map_gives_up_on_invalid_maps() {
+        use rand::SeedableRng;
 
-        (self.build_data.map, self.build_data.metadata)
+        let mut rng = MapRng::seed_from_u64(0);
+        let result = BuilderChain::new(20, 20)
+            .start_with(Box::new(NoopBuilder))
+            .build_map(&mut rng);
+        assert!(matches!(
+            result,
+            Err(validation::InvalidMapError::NotEnoughFloor(_))
+        ));
     }
 }
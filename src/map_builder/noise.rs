@@ -0,0 +1,110 @@
+use rand::seq::SliceRandom;
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use super::MapRng;
+
+/// Gradients at the lattice points, evenly distributed around the unit circle
+const GRADIENTS: [(f32, f32); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    (-FRAC_1_SQRT_2, FRAC_1_SQRT_2),
+    (FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+    (-FRAC_1_SQRT_2, -FRAC_1_SQRT_2),
+];
+
+/// Seeded 2D gradient noise in the style of Ken Perlin's improved noise.
+/// The noise repeats every 256 units along both axes.
+pub(super) struct GradientNoise {
+    /// Random permutation of 0..256, stored twice to avoid wrapping the indices
+    permutation: Vec<usize>,
+}
+
+impl GradientNoise {
+    pub fn new(rng: &mut MapRng) -> Self {
+        let mut permutation: Vec<usize> = (0..256).collect();
+        permutation.shuffle(rng);
+        permutation.extend_from_within(..);
+        GradientNoise { permutation }
+    }
+
+    /// Returns the noise value at the given point, which lies roughly within [-0.7, 0.7].
+    /// The noise is zero at integer coordinates.
+    pub fn get(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let xi = (x0 as i32 & 255) as usize;
+        let yi = (y0 as i32 & 255) as usize;
+
+        let contribution = |ix: usize, iy: usize, dx: f32, dy: f32| {
+            let hash = self.permutation[self.permutation[ix] + iy];
+            let (gx, gy) = GRADIENTS[hash % GRADIENTS.len()];
+            gx * dx + gy * dy
+        };
+        let n00 = contribution(xi, yi, fx, fy);
+        let n10 = contribution(xi + 1, yi, fx - 1.0, fy);
+        let n01 = contribution(xi, yi + 1, fx, fy - 1.0);
+        let n11 = contribution(xi + 1, yi + 1, fx - 1.0, fy - 1.0);
+
+        let (u, v) = (fade(fx), fade(fy));
+        lerp(lerp(n00, n10, u), lerp(n01, n11, u), v)
+    }
+
+    /// Sums several octaves of noise, each with twice the frequency and half the amplitude of the
+    /// previous one ('fractal Brownian motion'). The result has the same range as [`Self::get`].
+    pub fn fbm(&self, x: f32, y: f32, octaves: u32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut max_amplitude = 0.0;
+        for _ in 0..octaves {
+            total += amplitude * self.get(x * frequency, y * frequency);
+            max_amplitude += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        if max_amplitude > 0.0 {
+            total / max_amplitude
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Smoothstep-like curve with vanishing first and second derivatives at 0 and 1
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_noise_is_seeded() {
+        let a = GradientNoise::new(&mut MapRng::seed_from_u64(42));
+        let b = GradientNoise::new(&mut MapRng::seed_from_u64(42));
+        let c = GradientNoise::new(&mut MapRng::seed_from_u64(43));
+
+        let points = (0..100).map(|i| (i as f32 * 0.37, i as f32 * 0.21));
+        assert!(points.clone().all(|(x, y)| a.get(x, y) == b.get(x, y)));
+        assert!(points.clone().any(|(x, y)| a.get(x, y) != c.get(x, y)));
+        assert!(points.map(|(x, y)| a.fbm(x, y, 4)).all(|n| n.abs() <= 1.0));
+    }
+
+    #[test]
+    fn test_noise_vanishes_on_lattice() {
+        let noise = GradientNoise::new(&mut MapRng::seed_from_u64(42));
+        for (x, y) in [(0.0, 0.0), (3.0, 7.0), (255.0, 1.0), (-4.0, 12.0)] {
+            assert_eq!(noise.get(x, y), 0.0);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use super::{noise::GradientNoise, InitialMapBuilder, MapBuildData, MapRng};
+use crate::map::TileType;
+
+/// Number of noise octaves used for the elevation and moisture layers
+const OCTAVES: u32 = 4;
+
+/// Elevation band above the water level where the water is shallow enough to wade through
+const SHORE_WIDTH: f32 = 0.06;
+
+/// Maximum distance from the zero line of the river noise that is covered by a river
+const RIVER_WIDTH: f32 = 0.02;
+
+/// Moisture above which grass grows
+const GRASS_MOISTURE: f32 = -0.05;
+
+/// Creates outdoor terrain from several layers of seeded gradient noise:
+/// - an elevation layer determines lakes (low) and rock outcrops (high)
+/// - the zero line of a river layer carves shallow rivers through the land
+/// - a moisture layer decides between grass fields and bare ground
+///
+/// The outermost tiles of the map remain walls.
+pub struct NoiseTerrainBuilder {
+    /// Approximate size of terrain features in tiles
+    feature_size: f32,
+    /// Elevation below which lakes form, noise values lie roughly within [-0.7, 0.7]
+    water_level: f32,
+    /// Elevation above which rock outcrops form
+    rock_level: f32,
+}
+
+impl NoiseTerrainBuilder {
+    pub fn new(feature_size: f32, water_level: f32, rock_level: f32) -> Box<NoiseTerrainBuilder> {
+        Box::new(NoiseTerrainBuilder {
+            feature_size,
+            water_level,
+            rock_level,
+        })
+    }
+
+    /// Returns a closure that samples the given noise on the map, scaled to a multiple of the feature size
+    fn sampler(
+        &self,
+        noise: GradientNoise,
+        octaves: u32,
+        stretch: f32,
+    ) -> impl Fn(u32, u32) -> f32 {
+        let scale = (stretch * self.feature_size).max(1.0);
+        move |x, y| noise.fbm(x as f32 / scale, y as f32 / scale, octaves)
+    }
+}
+
+impl InitialMapBuilder for NoiseTerrainBuilder {
+    fn build_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let elevation = self.sampler(GradientNoise::new(rng), OCTAVES, 1.0);
+        // A single, stretched octave keeps the rivers smooth and few
+        let river = self.sampler(GradientNoise::new(rng), 1, 2.5);
+        let moisture = self.sampler(GradientNoise::new(rng), OCTAVES, 1.0);
+
+        let (width, height) = (build_data.map.width, build_data.map.height);
+        let interior = || (1..height - 1).flat_map(|y| (1..width - 1).map(move |x| (x, y)));
+
+        for (x, y) in interior() {
+            let idx = build_data.map.xy_to_idx(x, y).unwrap();
+            let e = elevation(x, y);
+            build_data.map.tiles[idx] = if e < self.water_level {
+                TileType::DeepWater
+            } else if e < self.water_level + SHORE_WIDTH {
+                TileType::ShallowWater
+            } else if e > self.rock_level {
+                TileType::Wall
+            } else {
+                TileType::Floor
+            };
+        }
+        build_data.take_snapshot();
+
+        // Rivers flow around the rocks, but connect to the lakes
+        for (x, y) in interior() {
+            let idx = build_data.map.xy_to_idx(x, y).unwrap();
+            if build_data.map.tiles[idx] == TileType::Floor && river(x, y).abs() < RIVER_WIDTH {
+                build_data.map.tiles[idx] = TileType::ShallowWater;
+            }
+        }
+        build_data.take_snapshot();
+
+        for (x, y) in interior() {
+            let idx = build_data.map.xy_to_idx(x, y).unwrap();
+            if build_data.map.tiles[idx] == TileType::Floor && moisture(x, y) > GRASS_MOISTURE {
+                build_data.map.tiles[idx] = TileType::Grass;
+            }
+        }
+        build_data.take_snapshot();
+    }
+}
@@ -0,0 +1,275 @@
+use rand::Rng;
+
+use super::{
+    random_table::RandomTable, rect::Rect, spawner::Spawnables, MapBuildData, MapModifier, MapRng,
+    SpawnError,
+};
+use crate::map::TileType;
+
+/// Determines where a [`Vault`] may be stamped onto the map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VaultPlacement {
+    /// Only overwrites wall tiles, at least one floor tile on the vault's edge must connect to a walkable tile of the existing map
+    ReplaceWalls,
+    /// Fits completely inside the interior of one of the [`MapMetadata::rooms`](super::MapMetadata::rooms)
+    InsideRoom,
+}
+
+/// A hand-designed set piece described by an ASCII template (one string per row).
+/// `#` marks walls, `.` marks floor tiles, and other symbols mark [`Spawnables`] placed on a floor tile.
+/// Objectives in a template are only placed if the map does not have one yet.
+#[derive(Debug)]
+pub struct Vault {
+    template: &'static [&'static str],
+    placement: VaultPlacement,
+}
+
+impl Vault {
+    fn width(&self) -> u32 {
+        self.template
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0) as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.template.len() as u32
+    }
+
+    /// Returns all tiles of the template with their offset from the top left corner
+    fn tiles(&self) -> impl Iterator<Item = (u32, u32, char)> + '_ {
+        self.template.iter().enumerate().flat_map(|(dy, row)| {
+            row.chars()
+                .enumerate()
+                .map(move |(dx, c)| (dx as u32, dy as u32, c))
+        })
+    }
+}
+
+/// A small treasure nook that opens towards the existing map
+const TREASURE_NOOK: Vault = Vault {
+    template: &["#####", "#.$.#", "#...#", "##.##"],
+    placement: VaultPlacement::ReplaceWalls,
+};
+
+/// A few turtles guarding the pillars in the middle of a room
+const TURTLE_PILLARS: Vault = Vault {
+    template: &[".....", ".#T#.", ".T.T.", ".#T#.", "....."],
+    placement: VaultPlacement::InsideRoom,
+};
+
+/// The well hidden home of Platino, see `assets/Dawnlike/README.txt`
+const PLATINO_SHRINE: Vault = Vault {
+    template: &["#######", "#.....#", "#..P..#", "#.....#", "###.###"],
+    placement: VaultPlacement::ReplaceWalls,
+};
+
+fn vault_table() -> RandomTable<&'static Vault> {
+    RandomTable::new()
+        .add(&TREASURE_NOOK, 6)
+        .add(&TURTLE_PILLARS, 3)
+        .add(&PLATINO_SHRINE, 1)
+}
+
+/// Stamps randomly selected [`Vault`]s onto an already generated map. Vaults never cover the starting
+/// position or anything that has already been added to the spawn list. Should be added after the
+/// objective has been placed, so that vaults don't add another one.
+pub struct PrefabVaults {
+    max_vaults: u32,
+}
+
+impl PrefabVaults {
+    pub fn new(max_vaults: u32) -> Box<PrefabVaults> {
+        Box::new(PrefabVaults { max_vaults })
+    }
+
+    /// Checks if the vault can be placed onto the given footprint
+    fn fits(&self, build_data: &MapBuildData, vault: &Vault, footprint: &Rect) -> bool {
+        let map = &build_data.map;
+        let metadata = &build_data.metadata;
+        if footprint.x2 >= map.width || footprint.y2 >= map.height {
+            return false;
+        }
+        let inside = |x: u32, y: u32| {
+            (footprint.x1..=footprint.x2).contains(&x) && (footprint.y1..=footprint.y2).contains(&y)
+        };
+        // Never cover anything that has been placed before
+        if build_data.reserved_positions().any(|(x, y)| inside(x, y)) {
+            return false;
+        }
+
+        match vault.placement {
+            VaultPlacement::ReplaceWalls => {
+                let is_wall = |x: u32, y: u32| {
+                    map.xy_to_idx(x, y)
+                        .is_ok_and(|idx| map.tiles[idx] == TileType::Wall)
+                };
+                let is_walkable = |x: u32, y: u32| {
+                    map.xy_to_idx(x, y)
+                        .is_ok_and(|idx| map.tiles[idx].is_walkable())
+                };
+                // Keep the outermost tiles of the map as walls
+                if footprint.x1 == 0 || footprint.y1 == 0 {
+                    return false;
+                }
+                if footprint.x2 + 1 >= map.width || footprint.y2 + 1 >= map.height {
+                    return false;
+                }
+                let all_walls = (footprint.y1..=footprint.y2)
+                    .all(|y| (footprint.x1..=footprint.x2).all(|x| is_wall(x, y)));
+                let connected = vault.tiles().any(|(dx, dy, c)| {
+                    let (x, y) = (footprint.x1 + dx, footprint.y1 + dy);
+                    c != '#'
+                        && [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                            .into_iter()
+                            .any(|(nx, ny)| !inside(nx, ny) && is_walkable(nx, ny))
+                });
+                all_walls && connected
+            }
+            VaultPlacement::InsideRoom => metadata.rooms.as_ref().is_some_and(|rooms| {
+                rooms.iter().any(|room| {
+                    // The walls of a room are never part of it
+                    let bounds = room.bounds();
+                    bounds.x1 < footprint.x1
+                        && bounds.x2 > footprint.x2
+                        && bounds.y1 < footprint.y1
+                        && bounds.y2 > footprint.y2
+                        && (footprint.y1..=footprint.y2)
+                            .all(|y| (footprint.x1..=footprint.x2).all(|x| room.contains(x, y)))
+                })
+            }),
+        }
+    }
+
+    fn stamp(&self, build_data: &mut MapBuildData, vault: &Vault, footprint: &Rect) {
+        let mut has_objective = build_data
+            .metadata
+            .spawn_list
+            .values()
+            .any(|s| s.is_objective());
+        for (dx, dy, c) in vault.tiles() {
+            let idx = build_data
+                .map
+                .xy_to_idx(footprint.x1 + dx, footprint.y1 + dy)
+                .unwrap();
+            build_data.map.tiles[idx] = match c {
+                '#' => TileType::Wall,
+                _ => TileType::Floor,
+            };
+        }
+        // Spawns are reserved once all tiles are in place to check their surroundings
+        for (dx, dy, c) in vault.tiles() {
+            let (x, y) = (footprint.x1 + dx, footprint.y1 + dy);
+            if let Some(s) = Spawnables::from_glyph(c) {
+                // The objective has been placed with care elsewhere, leave just a floor tile
+                if s.is_objective() && has_objective {
+                    continue;
+                }
+                has_objective |= s.is_objective();
+                match build_data.reserve_spawn((x, y), s) {
+                    // Leave a floor tile if the map already holds enough of the spawn
+                    Ok(()) | Err(SpawnError::LimitReached) => {}
+                    Err(SpawnError::Occupied) => panic!("Vaults never cover reserved tiles!"),
+                    Err(SpawnError::Chokepoint) => {
+                        panic!("Vaults surround immovable spawns with floor tiles!")
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl MapModifier for PrefabVaults {
+    fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let vaults = vault_table();
+        let mut placed: Vec<Rect> = Vec::new();
+
+        for _ in 0..self.max_vaults {
+            let vault = vaults.roll(rng).unwrap();
+            let (w, h) = (vault.width(), vault.height());
+            if w == 0 || h == 0 || w > build_data.map.width || h > build_data.map.height {
+                continue;
+            }
+
+            // Rect::new() spans width + 1 tiles, so the footprint covers exactly w x h tiles
+            let candidates: Vec<Rect> = (0..=build_data.map.height - h)
+                .flat_map(|y| (0..=build_data.map.width - w).map(move |x| (x, y)))
+                .map(|(x, y)| Rect::new(x, y, w - 1, h - 1))
+                .filter(|footprint| !placed.iter().any(|p| p.intersect(footprint)))
+                .filter(|footprint| self.fits(build_data, vault, footprint))
+                .collect();
+            if candidates.is_empty() {
+                continue;
+            }
+
+            let footprint = candidates[rng.gen_range(0..candidates.len())].clone();
+            self.stamp(build_data, vault, &footprint);
+            placed.push(footprint);
+            build_data.take_snapshot();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a 20x12 map with a wide hall at the bottom
+    fn build_data() -> MapBuildData {
+        let mut build_data = MapBuildData::new(20, 12);
+        for y in 8..=10 {
+            for x in 1..=18 {
+                let idx = build_data.map.xy_to_idx(x, y).unwrap();
+                build_data.map.tiles[idx] = TileType::Floor;
+            }
+        }
+        build_data
+    }
+
+    #[test]
+    fn test_fits() {
+        let vaults = PrefabVaults::new(1);
+        let mut build_data = build_data();
+
+        // The nook's opening at the bottom leads into the hall
+        assert!(vaults.fits(&build_data, &TREASURE_NOOK, &Rect::new(5, 4, 4, 3)));
+        // Overlapping the hall
+        assert!(!vaults.fits(&build_data, &TREASURE_NOOK, &Rect::new(5, 5, 4, 3)));
+        // Touching the map boundary
+        assert!(!vaults.fits(&build_data, &TREASURE_NOOK, &Rect::new(0, 4, 4, 3)));
+        // Not connected to the hall
+        assert!(!vaults.fits(&build_data, &TREASURE_NOOK, &Rect::new(5, 1, 4, 3)));
+        // No rooms to place the vault into
+        assert!(!vaults.fits(&build_data, &TURTLE_PILLARS, &Rect::new(3, 6, 4, 4)));
+
+        // Covering the starting position
+        build_data.metadata.starting_position = Some((7, 5));
+        assert!(!vaults.fits(&build_data, &TREASURE_NOOK, &Rect::new(5, 4, 4, 3)));
+    }
+
+    #[test]
+    fn test_stamp_places_a_single_objective() {
+        let vaults = PrefabVaults::new(1);
+        let footprint = Rect::new(5, 4, 4, 3);
+
+        let mut without_objective = build_data();
+        vaults.stamp(&mut without_objective, &TREASURE_NOOK, &footprint);
+        let map = &without_objective.map;
+        assert_eq!(map.tiles[map.xy_to_idx(7, 7).unwrap()], TileType::Floor);
+        assert_eq!(map.tiles[map.xy_to_idx(5, 7).unwrap()], TileType::Wall);
+        assert_eq!(
+            without_objective.metadata.spawn_list.get(&(7, 5)),
+            Some(&Spawnables::TreasureChest)
+        );
+
+        let mut with_objective = build_data();
+        with_objective
+            .reserve_spawn((15, 9), Spawnables::TreasureChest)
+            .unwrap();
+        vaults.stamp(&mut with_objective, &TREASURE_NOOK, &footprint);
+        let map = &with_objective.map;
+        assert_eq!(map.tiles[map.xy_to_idx(7, 5).unwrap()], TileType::Floor);
+        assert_eq!(with_objective.metadata.spawn_list.len(), 1);
+    }
+}
@@ -4,45 +4,97 @@ use std::fmt::Debug;
 pub struct TableEntry<S> {
     name: S,
     weight: i32,
+    /// Weight added for each level beyond `min_depth`, may be negative
+    weight_per_depth: i32,
+    min_depth: u32,
+    max_depth: Option<u32>,
 }
 
 impl<S> TableEntry<S> {
     pub fn new(name: S, weight: i32) -> Self {
-        TableEntry { name, weight }
+        TableEntry {
+            name,
+            weight,
+            weight_per_depth: 0,
+            min_depth: 1,
+            max_depth: None,
+        }
+    }
+
+    pub fn with_weight_per_depth(mut self, weight_per_depth: i32) -> Self {
+        self.weight_per_depth = weight_per_depth;
+        self
+    }
+
+    pub fn with_min_depth(mut self, min_depth: u32) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Returns the (non-negative) weight at the given depth, zero outside of the entry's depth range
+    fn weight_at(&self, depth: u32) -> i32 {
+        if depth < self.min_depth || self.max_depth.is_some_and(|max| depth > max) {
+            return 0;
+        }
+        let levels = (depth - self.min_depth) as i32;
+        (self.weight + self.weight_per_depth.saturating_mul(levels)).max(0)
     }
 }
 
 #[derive(Debug)]
 pub struct RandomTable<S> {
     entries: Vec<TableEntry<S>>,
-    total_weight: i32,
 }
 
 impl<S: Clone + Debug> RandomTable<S> {
     pub fn new() -> Self {
         RandomTable {
             entries: Vec::new(),
-            total_weight: 0,
         }
     }
 
-    pub fn add(mut self, name: S, weight: i32) -> Self {
-        self.total_weight += weight;
-        self.entries.push(TableEntry::new(name, weight));
+    pub fn add(self, name: S, weight: i32) -> Self {
+        self.add_entry(TableEntry::new(name, weight))
+    }
+
+    /// Adds an entry with depth dependent weights, see [`Self::roll_at_depth`]
+    pub fn add_entry(mut self, entry: TableEntry<S>) -> Self {
+        self.entries.push(entry);
         self
     }
 
+    /// Rolls an entry based on the weights it has been added with, ignoring depths
     pub fn roll<R: rand::Rng>(&self, rng: &mut R) -> Option<S> {
-        if self.total_weight == 0 {
+        self.roll_weighted(rng, |entry| entry.weight)
+    }
+
+    /// Rolls among the entries available at the given (1-based) depth using their depth dependent weights
+    pub fn roll_at_depth<R: rand::Rng>(&self, rng: &mut R, depth: u32) -> Option<S> {
+        self.roll_weighted(rng, |entry| entry.weight_at(depth))
+    }
+
+    fn roll_weighted<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+        weight: impl Fn(&TableEntry<S>) -> i32,
+    ) -> Option<S> {
+        let weights: Vec<i32> = self.entries.iter().map(weight).collect();
+        let total_weight: i32 = weights.iter().sum();
+        if total_weight <= 0 {
             return None;
         }
 
-        let mut roll = rng.gen_range(0..self.total_weight);
-        for entry in self.entries.iter() {
-            if roll < entry.weight {
+        let mut roll = rng.gen_range(0..total_weight);
+        for (entry, weight) in self.entries.iter().zip(weights) {
+            if roll < weight {
                 return Some(entry.name.clone());
             }
-            roll -= entry.weight;
+            roll -= weight;
         }
         panic!(
             "Did not find an entry in {:?} with a roll of {}",
@@ -50,3 +102,33 @@ impl<S: Clone + Debug> RandomTable<S> {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map_builder::MapRng;
+
+    #[test]
+    fn test_roll_at_depth() {
+        let table = RandomTable::new()
+            .add_entry(TableEntry::new('a', 10).with_max_depth(2))
+            .add_entry(
+                TableEntry::new('b', 1)
+                    .with_min_depth(2)
+                    .with_weight_per_depth(5),
+            );
+        let weights =
+            |depth| -> Vec<i32> { table.entries.iter().map(|e| e.weight_at(depth)).collect() };
+        assert_eq!(weights(1), [10, 0]);
+        assert_eq!(weights(2), [10, 1]);
+        assert_eq!(weights(4), [0, 11]);
+
+        let mut rng = MapRng::seed_from_u64(0);
+        for _ in 0..20 {
+            assert_eq!(table.roll_at_depth(&mut rng, 1), Some('a'));
+        }
+        assert_eq!(table.roll_at_depth(&mut rng, 3), Some('b'));
+    }
+}
@@ -0,0 +1,148 @@
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use super::rect::Rect;
+
+/// Purpose of a [`Region`] that determines what is spawned into it, see
+/// [`RegionTagger`](super::region_based_builders::RegionTagger)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum RegionTag {
+    /// Monsters gather here in numbers
+    Lair,
+    /// Nothing is spawned here, e.g. to give the player some room around the start
+    Quiet,
+    /// Contains an objective and a few guards
+    TreasureZone,
+}
+
+impl RegionTag {
+    /// All tags, ordered by precedence for regions with several tags
+    pub const ALL: [RegionTag; 3] = [RegionTag::TreasureZone, RegionTag::Lair, RegionTag::Quiet];
+}
+
+/// Represents a region of related tiles, e.g. to spawn monsters together in their lair
+/// Does not have to be a particular geometric shape (unlike a [`Room`](super::room::Room) which is
+/// limited to a few [`RoomShape`s](super::room::RoomShape))
+#[derive(Debug, Clone)]
+pub struct Region {
+    /// Index of the region in [`MapMetadata::regions`](super::MapMetadata::regions)
+    pub id: usize,
+    tiles: Vec<(u32, u32)>,
+    /// Smallest rectangle containing all tiles. Just like for [`Rect::new`], `x2` and `y2` lie one
+    /// tile beyond the region, so the width and height are the number of columns and rows it spans.
+    bounding_box: Rect,
+    /// Mean position of all tiles, which may lie outside of non-convex regions
+    centroid: (u32, u32),
+    /// Ids of the regions with a tile directly next to (not diagonally) a tile of this region
+    pub neighbors: Vec<usize>,
+    pub tags: HashSet<RegionTag>,
+}
+
+impl Region {
+    /// Turns sets of tiles into regions with consecutive ids, skipping empty sets. Each tile should
+    /// be part of a single set only.
+    pub fn from_tile_sets(tile_sets: impl IntoIterator<Item = Vec<(u32, u32)>>) -> Vec<Region> {
+        let mut regions: Vec<Region> = tile_sets
+            .into_iter()
+            .filter(|tiles| !tiles.is_empty())
+            .enumerate()
+            .map(|(id, tiles)| Region::new(id, tiles))
+            .collect();
+
+        let owner: HashMap<(u32, u32), usize> = regions
+            .iter()
+            .flat_map(|region| region.tiles.iter().map(|&pos| (pos, region.id)))
+            .collect();
+        for region in regions.iter_mut() {
+            let mut neighbors: Vec<usize> = region
+                .tiles
+                .iter()
+                .flat_map(|&(x, y)| {
+                    [
+                        (x.wrapping_sub(1), y),
+                        (x + 1, y),
+                        (x, y.wrapping_sub(1)),
+                        (x, y + 1),
+                    ]
+                })
+                .filter_map(|pos| owner.get(&pos).copied())
+                .filter(|&id| id != region.id)
+                .collect();
+            neighbors.sort_unstable();
+            neighbors.dedup();
+            region.neighbors = neighbors;
+        }
+        regions
+    }
+
+    fn new(id: usize, tiles: Vec<(u32, u32)>) -> Region {
+        let (mut x1, mut y1, mut x2, mut y2) = (u32::MAX, u32::MAX, 0, 0);
+        let (mut sum_x, mut sum_y) = (0u64, 0u64);
+        for &(x, y) in tiles.iter() {
+            x1 = x1.min(x);
+            y1 = y1.min(y);
+            x2 = x2.max(x);
+            y2 = y2.max(y);
+            sum_x += u64::from(x);
+            sum_y += u64::from(y);
+        }
+        let count = tiles.len() as u64;
+        Region {
+            id,
+            centroid: ((sum_x / count) as u32, (sum_y / count) as u32),
+            bounding_box: Rect::new(x1, y1, x2 - x1 + 1, y2 - y1 + 1),
+            tiles,
+            neighbors: Vec::new(),
+            tags: HashSet::new(),
+        }
+    }
+
+    pub fn tiles(&self) -> &[(u32, u32)] {
+        &self.tiles
+    }
+
+    pub fn bounding_box(&self) -> &Rect {
+        &self.bounding_box
+    }
+
+    pub fn centroid(&self) -> (u32, u32) {
+        self.centroid
+    }
+
+    pub fn has_tag(&self, tag: RegionTag) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    /// Returns the tag with the highest precedence (see [`RegionTag::ALL`]), if any
+    pub fn primary_tag(&self) -> Option<RegionTag> {
+        RegionTag::ALL.into_iter().find(|&tag| self.has_tag(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_tile_sets() {
+        // Two horizontally adjacent regions, an empty set, and a region only touching the second one diagonally
+        let regions = Region::from_tile_sets([
+            vec![(0, 0), (1, 0), (0, 1)],
+            vec![(2, 0), (3, 0)],
+            vec![],
+            vec![(4, 1)],
+        ]);
+
+        assert_eq!(regions.len(), 3);
+        assert_eq!(regions[2].id, 2);
+        assert_eq!(regions[0].neighbors, [1]);
+        assert_eq!(regions[1].neighbors, [0]);
+        assert!(regions[2].neighbors.is_empty());
+
+        let Rect { x1, x2, y1, y2 } = regions[0].bounding_box().clone();
+        assert_eq!((x1, x2, y1, y2), (0, 2, 0, 2));
+        assert_eq!(regions[1].bounding_box().width(), 2);
+        assert_eq!(regions[1].bounding_box().height(), 1);
+        assert_eq!(regions[1].centroid(), (2, 0));
+    }
+}
@@ -1,9 +1,14 @@
+use bevy::log::*;
 use std::iter::FromIterator;
 
 use rand::Rng;
+use serde::Deserialize;
 
-use super::{MapBuildData, MapModifier, MapRng, Region};
-use crate::map::TileType;
+use super::{
+    region::{Region, RegionTag},
+    spawner::{fill_region, spawn_table},
+    MapBuildData, MapModifier, MapRng,
+};
 
 /// Generates Voronoi regions around randomly selected seed points in which to spawn entities
 #[derive(Debug)]
@@ -12,30 +17,23 @@ pub struct VoronoiRegion {
     number_of_regions: u32,
 }
 
-/// Available distance functions to use in determining [`VoronoiRegion`]s
-#[derive(Debug, Clone, Copy, PartialEq)]
-#[allow(dead_code)]
+/// Available distance functions to use in determining [`VoronoiRegion`]s and Voronoi cells
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 pub enum DistanceFunction {
     Euclidean,
     Manhattan,
     Maximum,
 }
 
-impl VoronoiRegion {
-    pub fn new(number_of_regions: u32, distance_function: DistanceFunction) -> Box<VoronoiRegion> {
-        Box::new(VoronoiRegion {
-            distance_function,
-            number_of_regions,
-        })
-    }
-
-    fn find_closest_point(&self, p: (u32, u32), points: &[(u32, u32)]) -> usize {
+impl DistanceFunction {
+    /// Returns the index of the point closest to `p`
+    pub fn find_closest_point(&self, p: (u32, u32), points: &[(u32, u32)]) -> usize {
         let mut min_distance = u32::MAX;
         let mut min_idx = usize::MAX;
         for (idx, &(x, y)) in points.iter().enumerate() {
             let dx = p.0.abs_diff(x);
             let dy = p.1.abs_diff(y);
-            let distance = match self.distance_function {
+            let distance = match self {
                 // No need to take the square root to determine the closest point
                 DistanceFunction::Euclidean => dx * dx + dy * dy,
                 DistanceFunction::Manhattan => dx + dy,
@@ -52,6 +50,15 @@ impl VoronoiRegion {
     }
 }
 
+impl VoronoiRegion {
+    pub fn new(number_of_regions: u32, distance_function: DistanceFunction) -> Box<VoronoiRegion> {
+        Box::new(VoronoiRegion {
+            distance_function,
+            number_of_regions,
+        })
+    }
+}
+
 impl MapModifier for VoronoiRegion {
     fn modify_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
         let seed_points = {
@@ -65,23 +72,115 @@ impl MapModifier for VoronoiRegion {
             }))
         };
 
-        let mut regions = vec![Region::new(); self.number_of_regions as usize];
+        let mut regions = vec![Vec::new(); self.number_of_regions as usize];
         let map = &build_data.map;
         for x in 1..map.width - 1 {
             for y in 1..map.height - 1 {
                 let idx = map.xy_to_idx(x, y).unwrap();
-                if map.tiles[idx] == TileType::Floor {
-                    let region_idx = self.find_closest_point((x, y), &seed_points);
+                if map.tiles[idx].is_walkable() {
+                    let region_idx = self
+                        .distance_function
+                        .find_closest_point((x, y), &seed_points);
                     regions[region_idx].push((x, y));
                 }
             }
         }
 
-        build_data.metadata.regions = Some(regions);
+        build_data.metadata.regions = Some(Region::from_tile_sets(regions));
+        build_data.take_snapshot();
+    }
+}
+
+/// Criteria by which a [`RegionTagger`] picks its lairs and quiet regions
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum TaggingStrategy {
+    /// The largest regions become lairs and the smallest ones quiet
+    Size,
+    /// The regions farthest from the starting position become lairs and the closest ones quiet
+    DistanceFromStart,
+}
+
+/// Assigns [`RegionTag`]s to the regions of a map: the given fraction of regions each become
+/// [lairs](RegionTag::Lair) and [quiet](RegionTag::Quiet) according to the [`TaggingStrategy`],
+/// and regions containing an objective become [treasure zones](RegionTag::TreasureZone)
+pub struct RegionTagger {
+    strategy: TaggingStrategy,
+    fraction: f32,
+}
+
+impl RegionTagger {
+    pub fn new(strategy: TaggingStrategy, fraction: f32) -> Box<RegionTagger> {
+        Box::new(RegionTagger { strategy, fraction })
+    }
+}
+
+impl MapModifier for RegionTagger {
+    fn modify_map(&mut self, _rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let metadata = &build_data.metadata;
+        let Some(regions) = metadata.regions.as_ref() else {
+            warn!("Cannot tag regions without any regions!");
+            return;
+        };
+
+        // Sort the region ids in ascending order of the strategy's criterion
+        let mut ids: Vec<usize> = (0..regions.len()).collect();
+        match self.strategy {
+            TaggingStrategy::Size => ids.sort_by_key(|&id| regions[id].tiles().len()),
+            TaggingStrategy::DistanceFromStart => {
+                let Some(start) = metadata.starting_position else {
+                    warn!("Cannot tag regions by their distance without a starting position!");
+                    return;
+                };
+                let path_lengths = build_data.map.path_lengths(start);
+                // Unreachable regions count as the farthest ones
+                let distance = |region: &Region| {
+                    region
+                        .tiles()
+                        .iter()
+                        .map(|pos| match path_lengths.get(pos) {
+                            // The start itself is not part of its path lengths
+                            _ if *pos == start => 0,
+                            Some(&distance) => distance,
+                            None => u32::MAX,
+                        })
+                        .min()
+                };
+                ids.sort_by_key(|&id| distance(&regions[id]));
+            }
+        }
+        let count = ((regions.len() as f32 * self.fraction.clamp(0.0, 1.0)).round() as usize)
+            .min(regions.len() / 2);
+        let quiet = ids[..count].to_vec();
+        let lairs = ids[ids.len() - count..].to_vec();
+
+        let objectives: Vec<(u32, u32)> = metadata
+            .spawn_list
+            .iter()
+            .filter(|(_, spawn)| spawn.is_objective())
+            .map(|(&pos, _)| pos)
+            .collect();
+        let regions = build_data
+            .metadata
+            .regions
+            .as_mut()
+            .expect("Regions have been checked above!");
+        for id in quiet {
+            regions[id].tags.insert(RegionTag::Quiet);
+        }
+        for id in lairs {
+            regions[id].tags.insert(RegionTag::Lair);
+        }
+        for region in regions.iter_mut() {
+            if region.tiles().iter().any(|pos| objectives.contains(pos)) {
+                region.tags.insert(RegionTag::TreasureZone);
+            }
+        }
         build_data.take_snapshot();
     }
 }
 
+/// Spawns into each region according to its [`Region::primary_tag`], see [`spawn_table`].
+/// Lairs receive twice as many spawns as other regions.
 pub struct RegionBasedSpawner {
     max_spawns: u32,
 }
@@ -97,18 +196,104 @@ impl MapModifier for RegionBasedSpawner {
         let regions = build_data
             .metadata
             .regions
-            .as_ref()
+            .clone()
             .expect("Need regions to spawn into!");
 
-        let mut spawn_lists = Vec::with_capacity(regions.len());
         for r in regions.iter() {
-            let spawn_list = super::spawner::fill_region(rng, r, self.max_spawns);
-            spawn_lists.push(spawn_list);
-        }
-        // Take a snapshot after each separate region has been populated
-        for spawn_list in spawn_lists {
-            build_data.metadata.spawn_list.extend(spawn_list);
+            let tag = r.primary_tag();
+            let max_spawns = match tag {
+                Some(RegionTag::Lair) => 2 * self.max_spawns,
+                _ => self.max_spawns,
+            };
+            fill_region(rng, build_data, &spawn_table(tag), r.tiles(), max_spawns);
+            // Take a snapshot after each separate region has been populated
             build_data.take_snapshot();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map::TileType;
+    use crate::map_builder::spawner::Spawnables;
+
+    /// A corridor split into regions of 2, 6 and 12 tiles, starting at its east end
+    fn corridor() -> MapBuildData {
+        let mut build_data = MapBuildData::new(22, 3);
+        for x in 1..=20 {
+            let idx = build_data.map.xy_to_idx(x, 1).unwrap();
+            build_data.map.tiles[idx] = TileType::Floor;
+        }
+        let tiles = |xs: std::ops::RangeInclusive<u32>| xs.map(|x| (x, 1)).collect();
+        build_data.metadata.regions = Some(Region::from_tile_sets([
+            tiles(1..=2),
+            tiles(3..=8),
+            tiles(9..=20),
+        ]));
+        build_data.reserve_starting_position((20, 1)).unwrap();
+        build_data
+    }
+
+    fn tags(build_data: &MapBuildData) -> Vec<Option<RegionTag>> {
+        let regions = build_data.metadata.regions.as_ref().unwrap();
+        regions.iter().map(Region::primary_tag).collect()
+    }
+
+    #[test]
+    fn test_tagging_strategies() {
+        let mut rng = MapRng::seed_from_u64(0);
+        let mut by_size = corridor();
+        RegionTagger::new(TaggingStrategy::Size, 0.3).modify_map(&mut rng, &mut by_size);
+        assert_eq!(
+            tags(&by_size),
+            [Some(RegionTag::Quiet), None, Some(RegionTag::Lair)]
+        );
+
+        let mut by_distance = corridor();
+        by_distance
+            .reserve_spawn((5, 1), Spawnables::TreasureChest)
+            .unwrap();
+        RegionTagger::new(TaggingStrategy::DistanceFromStart, 0.3)
+            .modify_map(&mut rng, &mut by_distance);
+        assert_eq!(
+            tags(&by_distance),
+            [
+                Some(RegionTag::Lair),
+                Some(RegionTag::TreasureZone),
+                Some(RegionTag::Quiet)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spawns_follow_tags() {
+        // Three open areas of equal size, the first one quiet and the second one a lair
+        let mut spawns = [0; 3];
+        for seed in 0..20 {
+            let mut build_data = MapBuildData::new(32, 12);
+            let mut areas = vec![Vec::new(); 3];
+            for x in 1..31 {
+                for y in 1..11 {
+                    let idx = build_data.map.xy_to_idx(x, y).unwrap();
+                    build_data.map.tiles[idx] = TileType::Floor;
+                    areas[(x as usize - 1) / 10].push((x, y));
+                }
+            }
+            let mut regions = Region::from_tile_sets(areas);
+            regions[0].tags.insert(RegionTag::Quiet);
+            regions[1].tags.insert(RegionTag::Lair);
+            build_data.metadata.regions = Some(regions);
+
+            let mut rng = MapRng::seed_from_u64(seed);
+            RegionBasedSpawner::new(3).modify_map(&mut rng, &mut build_data);
+            for &(x, _) in build_data.metadata.spawn_list.keys() {
+                spawns[(x as usize - 1) / 10] += 1;
+            }
+        }
+        assert_eq!(spawns[0], 0);
+        assert!(spawns[1] > spawns[2], "{spawns:?}");
+    }
+}
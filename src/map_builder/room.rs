@@ -0,0 +1,234 @@
+use rand::Rng;
+use serde::Deserialize;
+
+use super::{rect::Rect, MapRng};
+
+/// Corners of a [`Rect`], with 'top' pointing towards increasing y coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Corner {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+/// Available shapes of a [`Room`]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum RoomShape {
+    Rectangle,
+    /// Rectangle with cut off corners
+    RoundedRectangle,
+    /// Circle (or rather disk) that fits into the bounding box, a rectangle of the same size remains unused
+    Circle,
+    /// A horizontal and a vertical bar crossing at the center
+    Cross,
+    /// Rectangle with the quarter beyond the center towards the given corner removed
+    LShape(Corner),
+}
+
+impl RoomShape {
+    /// Randomly rotates asymmetric shapes and leaves all other shapes unchanged
+    pub fn with_random_orientation(self, rng: &mut MapRng) -> Self {
+        match self {
+            RoomShape::LShape(_) => RoomShape::LShape(match rng.gen_range(0..4) {
+                0 => Corner::BottomLeft,
+                1 => Corner::BottomRight,
+                2 => Corner::TopLeft,
+                _ => Corner::TopRight,
+            }),
+            shape => shape,
+        }
+    }
+}
+
+/// A room of a given [`RoomShape`] within a bounding box. Just like for [`Rect`] rooms, the
+/// outermost tiles of the bounding box are reserved for the room's walls.
+#[derive(Debug, Clone)]
+pub struct Room {
+    pub shape: RoomShape,
+    bounds: Rect,
+    /// Whether the shape is flipped along the x or y axis within the bounding box, which is
+    /// required for exact mirror images since shapes are not perfectly symmetric on every grid
+    flipped: (bool, bool),
+}
+
+impl Room {
+    pub fn new(shape: RoomShape, bounds: Rect) -> Self {
+        Room {
+            shape,
+            bounds,
+            flipped: (false, false),
+        }
+    }
+
+    /// Returns the bounding box, including the room's walls
+    pub fn bounds(&self) -> &Rect {
+        &self.bounds
+    }
+
+    /// Returns the (flipped) center of the bounding box which is always one of the room's tiles
+    pub fn center(&self) -> (u32, u32) {
+        let Rect { x1, x2, y1, y2 } = self.bounds;
+        let (cx, cy) = self.bounds.center();
+        (
+            if self.flipped.0 { x1 + x2 - cx } else { cx },
+            if self.flipped.1 { y1 + y2 - cy } else { cy },
+        )
+    }
+
+    /// Returns the mirror image of this room on a map of the given width, i.e. with `x` mapped
+    /// to `width - 1 - x`
+    pub fn mirrored_horizontally(&self, width: u32) -> Room {
+        let Rect { x1, x2, y1, y2 } = self.bounds;
+        Room {
+            shape: self.shape,
+            bounds: Rect {
+                x1: width - 1 - x2,
+                x2: width - 1 - x1,
+                y1,
+                y2,
+            },
+            flipped: (!self.flipped.0, self.flipped.1),
+        }
+    }
+
+    /// Returns the mirror image of this room on a map of the given height, i.e. with `y` mapped
+    /// to `height - 1 - y`
+    pub fn mirrored_vertically(&self, height: u32) -> Room {
+        let Rect { x1, x2, y1, y2 } = self.bounds;
+        Room {
+            shape: self.shape,
+            bounds: Rect {
+                x1,
+                x2,
+                y1: height - 1 - y2,
+                y2: height - 1 - y1,
+            },
+            flipped: (self.flipped.0, !self.flipped.1),
+        }
+    }
+
+    /// Conservatively checks if two rooms intersect based on their bounding boxes
+    pub fn intersect(&self, other: &Room) -> bool {
+        self.bounds.intersect(&other.bounds)
+    }
+
+    /// Checks if the given position is one of the room's floor tiles
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        let Rect { x1, x2, y1, y2 } = self.bounds;
+        if x <= x1 || x >= x2 || y <= y1 || y >= y2 {
+            return false;
+        }
+        // Evaluate flipped shapes at the mirrored position
+        let x = if self.flipped.0 { x1 + x2 - x } else { x };
+        let y = if self.flipped.1 { y1 + y2 - y } else { y };
+        let (cx, cy) = self.bounds.center();
+        // Interior dimensions and the position relative to the interior's bottom left corner
+        let (w, h) = (x2 - x1 - 1, y2 - y1 - 1);
+        let (rx, ry) = (x - x1 - 1, y - y1 - 1);
+
+        match self.shape {
+            RoomShape::Rectangle => true,
+            // Rooms that are too narrow would lose their center
+            RoomShape::RoundedRectangle if w < 3 || h < 3 => true,
+            RoomShape::RoundedRectangle => {
+                let radius = (w.min(h) / 4).max(1);
+                let dx = rx.min(w - 1 - rx);
+                let dy = ry.min(h - 1 - ry);
+                dx + dy >= radius
+            }
+            RoomShape::Circle => {
+                let radius = w.min(h) as f32 / 2.0;
+                let dx = x as f32 - cx as f32;
+                let dy = y as f32 - cy as f32;
+                // The additional radius avoids single tiles sticking out at the sides
+                dx * dx + dy * dy <= radius * (radius + 0.5)
+            }
+            RoomShape::Cross => {
+                let in_horizontal_bar = (h / 3..h - h / 3).contains(&ry);
+                let in_vertical_bar = (w / 3..w - w / 3).contains(&rx);
+                in_horizontal_bar || in_vertical_bar
+            }
+            RoomShape::LShape(corner) => {
+                let removed = match corner {
+                    Corner::BottomLeft => x < cx && y < cy,
+                    Corner::BottomRight => x > cx && y < cy,
+                    Corner::TopLeft => x < cx && y > cy,
+                    Corner::TopRight => x > cx && y > cy,
+                };
+                !removed
+            }
+        }
+    }
+
+    /// Returns all floor tiles of this room
+    pub fn tiles(&self) -> Vec<(u32, u32)> {
+        let Rect { x1, x2, y1, y2 } = self.bounds;
+        (y1 + 1..y2)
+            .flat_map(|y| (x1 + 1..x2).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.contains(x, y))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_center_is_part_of_every_shape() {
+        let shapes = [
+            RoomShape::Rectangle,
+            RoomShape::RoundedRectangle,
+            RoomShape::Circle,
+            RoomShape::Cross,
+            RoomShape::LShape(Corner::BottomLeft),
+            RoomShape::LShape(Corner::BottomRight),
+            RoomShape::LShape(Corner::TopLeft),
+            RoomShape::LShape(Corner::TopRight),
+        ];
+        for shape in shapes {
+            for (w, h) in [(2, 2), (3, 3), (4, 7), (9, 5), (12, 12)] {
+                let room = Room::new(shape, Rect::new(3, 5, w, h));
+                let tiles = room.tiles();
+                assert!(tiles.contains(&room.center()), "{shape:?} {w}x{h}");
+                assert!(tiles
+                    .iter()
+                    .all(|&(x, y)| x > 3 && x < 3 + w && y > 5 && y < 5 + h));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mirrored_rooms_are_exact_mirror_images() {
+        let (width, height) = (40, 30);
+        for shape in [RoomShape::Circle, RoomShape::LShape(Corner::TopRight)] {
+            let room = Room::new(shape, Rect::new(3, 5, 7, 10));
+            let mut expected: Vec<(u32, u32)> = room
+                .tiles()
+                .into_iter()
+                .map(|(x, y)| (width - 1 - x, height - 1 - y))
+                .collect();
+            expected.sort();
+            let mirrored = room
+                .mirrored_horizontally(width)
+                .mirrored_vertically(height);
+            let mut tiles = mirrored.tiles();
+            tiles.sort();
+            assert_eq!(tiles, expected, "{shape:?}");
+            assert!(tiles.contains(&mirrored.center()));
+        }
+    }
+
+    #[test]
+    fn test_shape_sizes() {
+        let bounds = Rect::new(0, 0, 6, 6);
+        let size = |shape| Room::new(shape, bounds.clone()).tiles().len();
+
+        assert_eq!(size(RoomShape::Rectangle), 25);
+        assert_eq!(size(RoomShape::RoundedRectangle), 21);
+        assert_eq!(size(RoomShape::Cross), 21);
+        assert_eq!(size(RoomShape::LShape(Corner::TopRight)), 21);
+        assert_eq!(size(RoomShape::Circle), 21);
+    }
+}
@@ -1,13 +1,16 @@
-use rand::Rng;
+use bevy::log::*;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
 
 use super::{
-    rect::Rect,
-    spawner::{fill_room, Spawnables},
-    MapBuildData, MapModifier, MapRng, SpawnList,
+    room::Room,
+    spawner::{fill_region, spawn_table, Spawnables},
+    MapBuildData, MapModifier, MapRng,
 };
 
 const ROOMS_REQUIRED_ERROR: &str =
     "Room based spawning requires rooms to have been generated first!";
+const FREE_TILE_ERROR: &str = "Spawn positions are selected among free tiles only!";
 
 pub struct RoomBasedSpawner {
     max_spawns: u32,
@@ -17,13 +20,6 @@ impl RoomBasedSpawner {
     pub fn new(max_spawns: u32) -> Box<RoomBasedSpawner> {
         Box::new(RoomBasedSpawner { max_spawns })
     }
-
-    fn spawn(&mut self, rng: &mut MapRng, rooms: &[Rect]) -> SpawnList {
-        rooms
-            .iter()
-            .flat_map(|room| fill_room(rng, room, self.max_spawns))
-            .collect()
-    }
 }
 
 impl MapModifier for RoomBasedSpawner {
@@ -31,26 +27,30 @@ impl MapModifier for RoomBasedSpawner {
         let rooms = build_data
             .metadata
             .rooms
-            .as_ref()
+            .clone()
             .expect(ROOMS_REQUIRED_ERROR);
-        build_data
-            .metadata
-            .spawn_list
-            .extend(self.spawn(rng, rooms));
+        let spawn_table = spawn_table(None);
+        for room in rooms.iter() {
+            fill_region(
+                rng,
+                build_data,
+                &spawn_table,
+                &room.tiles(),
+                self.max_spawns,
+            );
+        }
         build_data.take_snapshot();
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-/// TODO: Remove this lint-silencing once a use for [`RoomSelectionMode::Random`] has been found
-#[allow(dead_code)]
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
 pub enum RoomSelectionMode {
     First,
     Last,
     Random,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
 pub enum PositionSelectionMode {
     Center,
     Random,
@@ -78,15 +78,20 @@ impl MapModifier for RoomBasedStartingPosition {
             .rooms
             .as_ref()
             .expect(ROOMS_REQUIRED_ERROR);
-        let start_room = select_room(&self.room_mode, rooms, rng);
-        // FIXME: This can select the same tile as another spawning MapModifier
-        let start_pos = select_position(&self.pos_mode, start_room, rng);
-        build_data.metadata.starting_position = Some(start_pos);
+        let Some(start_pos) = select_room(&self.room_mode, rooms, rng)
+            .and_then(|room| select_position(&self.pos_mode, room, build_data, rng))
+        else {
+            warn!("Cannot find a free tile for the starting position!");
+            return;
+        };
+        build_data
+            .reserve_starting_position(start_pos)
+            .expect(FREE_TILE_ERROR);
         build_data.take_snapshot();
     }
 }
 
-/// Select the player's starting position from one of the available rooms
+/// Place an objective in one of the available rooms
 pub struct RoomBasedObjectiveSpawner {
     room_mode: RoomSelectionMode,
     pos_mode: PositionSelectionMode,
@@ -114,30 +119,52 @@ impl MapModifier for RoomBasedObjectiveSpawner {
             .rooms
             .as_ref()
             .expect(ROOMS_REQUIRED_ERROR);
-        let room = select_room(&self.room_mode, rooms, rng);
-        // FIXME: This can select the same tile as another spawning MapModifier
-        let pos = select_position(&self.pos_mode, room, rng);
-        build_data.metadata.spawn_list.insert(pos, self.objective);
+        let Some(pos) = select_room(&self.room_mode, rooms, rng)
+            .and_then(|room| select_position(&self.pos_mode, room, build_data, rng))
+        else {
+            warn!("Cannot find a free tile for the {:?}!", self.objective);
+            return;
+        };
+        build_data
+            .reserve_spawn(pos, self.objective)
+            .expect(FREE_TILE_ERROR);
         build_data.take_snapshot();
     }
 }
 
-fn select_room<'a>(mode: &RoomSelectionMode, rooms: &'a Vec<Rect>, rng: &mut MapRng) -> &'a Rect {
-    let num_rooms = rooms.len();
+/// Returns `None` if there are no rooms
+fn select_room<'a>(
+    mode: &RoomSelectionMode,
+    rooms: &'a [Room],
+    rng: &mut MapRng,
+) -> Option<&'a Room> {
     match mode {
-        RoomSelectionMode::First => &rooms[0],
-        RoomSelectionMode::Last => &rooms[num_rooms - 1],
-        RoomSelectionMode::Random => &rooms[rng.gen_range(0..num_rooms)],
+        RoomSelectionMode::First => rooms.first(),
+        RoomSelectionMode::Last => rooms.last(),
+        RoomSelectionMode::Random => rooms.choose(rng),
     }
 }
 
-fn select_position(mode: &PositionSelectionMode, room: &Rect, rng: &mut MapRng) -> (u32, u32) {
-    match mode {
-        PositionSelectionMode::Center => room.center(),
-        PositionSelectionMode::Random => {
-            let rx = rng.gen_range(room.x1 + 1..room.x2);
-            let ry = rng.gen_range(room.y1 + 1..room.y2);
-            (rx, ry)
-        }
+/// Selects a free position within the room. Occupied centers are re-rolled within the room and
+/// any other free tile of the map is used if the room is fully occupied. Returns `None` if there
+/// are no free tiles at all.
+fn select_position(
+    mode: &PositionSelectionMode,
+    room: &Room,
+    build_data: &MapBuildData,
+    rng: &mut MapRng,
+) -> Option<(u32, u32)> {
+    if *mode == PositionSelectionMode::Center && build_data.is_free(room.center()) {
+        return Some(room.center());
+    }
+    let free_tiles: Vec<(u32, u32)> = room
+        .tiles()
+        .into_iter()
+        .filter(|&pos| build_data.is_free(pos))
+        .collect();
+    if let Some(&pos) = free_tiles.choose(rng) {
+        return Some(pos);
     }
+    warn!("All tiles of the selected room are occupied, using any other free tile instead!");
+    build_data.free_tiles().choose(rng).copied()
 }
@@ -2,55 +2,61 @@ use bevy::log::*;
 use rand::Rng;
 use std::cmp::{max, min};
 
-use super::{rect::Rect, InitialMapBuilder, MapBuildData, MapRng};
+use super::{
+    random_table::RandomTable,
+    rect::Rect,
+    room::{Room, RoomShape},
+    InitialMapBuilder, MapBuildData, MapRng,
+};
 use crate::map::{GameMap, TileType};
 
+/// Places randomly sized, non-overlapping rooms with shapes rolled from a [`RandomTable`].
+/// Connecting them is left to one of the [`corridors`](super::corridors) modifiers.
 pub struct SimpleMapBuilder {
     max_rooms: u32,
     min_size: u32,
     max_size: u32,
+    shapes: RandomTable<RoomShape>,
 }
 
 impl InitialMapBuilder for SimpleMapBuilder {
     fn build_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
-        self.rooms_and_corridors(rng, build_data);
+        self.rooms(rng, build_data);
     }
 }
 
 impl SimpleMapBuilder {
-    pub fn new(max_rooms: u32, min_size: u32, max_size: u32) -> Box<SimpleMapBuilder> {
+    pub fn new(
+        max_rooms: u32,
+        min_size: u32,
+        max_size: u32,
+        shapes: RandomTable<RoomShape>,
+    ) -> Box<SimpleMapBuilder> {
         Box::new(SimpleMapBuilder {
             max_rooms,
             min_size,
             max_size,
+            shapes,
         })
     }
 
-    fn rooms_and_corridors(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+    fn rooms(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
         let mut rooms = Vec::new();
 
         for _ in 0..self.max_rooms {
             let w = rng.gen_range(self.min_size..=self.max_size);
             let h = rng.gen_range(self.min_size..=self.max_size);
+            // Leave room for the walls between the room and the map's edge
+            if w + 2 > build_data.map.width || h + 2 > build_data.map.height {
+                continue;
+            }
             let x = rng.gen_range(0..build_data.map.width - w - 1);
             let y = rng.gen_range(0..build_data.map.height - h - 1);
-            let new_room = Rect::new(x, y, w, h);
+            let shape = self.shapes.roll(rng).unwrap_or(RoomShape::Rectangle);
+            let new_room = Room::new(shape.with_random_orientation(rng), Rect::new(x, y, w, h));
             let ok = !rooms.iter().any(|r| new_room.intersect(r));
             if ok {
                 apply_room_to_map(&mut build_data.map, &new_room);
-                if !rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
-                    apply_tunnel(
-                        &mut build_data.map,
-                        prev_x,
-                        prev_y,
-                        new_x,
-                        new_y,
-                        rng.gen_bool(0.5),
-                    );
-                }
-
                 rooms.push(new_room);
                 build_data.take_snapshot();
             }
@@ -59,24 +65,29 @@ impl SimpleMapBuilder {
     }
 }
 
-/// Marks all tiles strictly inside the given room as floor tiles
-fn apply_room_to_map(map: &mut GameMap, room: &Rect) {
-    for y in room.y1 + 1..room.y2 {
-        for x in room.x1 + 1..room.x2 {
-            if let Ok(idx) = map.xy_to_idx(x, y) {
-                map.tiles[idx] = TileType::Floor;
-            } else {
-                warn!(
-                    "Cannot make {x}, {y} part of a room, it is outside the map ({} x {})!",
-                    map.width, map.height
-                );
-            }
+/// Marks all tiles of the given room as floor tiles
+pub(super) fn apply_room_to_map(map: &mut GameMap, room: &Room) {
+    for (x, y) in room.tiles() {
+        if let Ok(idx) = map.xy_to_idx(x, y) {
+            map.tiles[idx] = TileType::Floor;
+        } else {
+            warn!(
+                "Cannot make {x}, {y} part of a room, it is outside the map ({} x {})!",
+                map.width, map.height
+            );
         }
     }
 }
 
 /// Carves a dogleg path of floor tiles into the map from (x1, y1) to (x2, y2)
-fn apply_tunnel(map: &mut GameMap, x1: u32, y1: u32, x2: u32, y2: u32, horizontal_first: bool) {
+pub(super) fn apply_tunnel(
+    map: &mut GameMap,
+    x1: u32,
+    y1: u32,
+    x2: u32,
+    y2: u32,
+    horizontal_first: bool,
+) {
     if horizontal_first {
         apply_horizontal_tunnel(map, x1, x2, y1);
         apply_vertical_tunnel(map, y1, y2, x2);
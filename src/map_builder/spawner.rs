@@ -1,59 +1,147 @@
 use rand::Rng;
-use std::collections::hash_map::Entry;
+use serde::{Deserialize, Serialize};
 
-use super::{random_table::RandomTable, rect::Rect, MapRng, SpawnList};
+use super::{
+    random_table::{RandomTable, TableEntry},
+    region::RegionTag,
+    MapBuildData, MapRng, SpawnError,
+};
 
 /// All things that can be spawned onto a map
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub enum Spawnables {
     /// Used to mark spawn positions that are already blocked, e.g. player start positions
     TreasureChest,
     Turtle,
+    /// A very well hidden friend, see `assets/Dawnlike/README.txt`
+    Platino,
+    /// A closed door that opens when the player bumps into it
+    Door,
 }
 
-fn spawn_table() -> RandomTable<Spawnables> {
-    use Spawnables::*;
-    RandomTable::new().add(Turtle, 10)
-}
+impl Spawnables {
+    /// All variants, e.g. to report statistics on each of them
+    pub const ALL: [Spawnables; 4] = [
+        Spawnables::TreasureChest,
+        Spawnables::Turtle,
+        Spawnables::Platino,
+        Spawnables::Door,
+    ];
+
+    /// Returns the [`Spawnables`] represented by a character in hand-written map templates
+    pub fn from_glyph(glyph: char) -> Option<Self> {
+        use Spawnables::*;
+        match glyph {
+            '$' => Some(TreasureChest),
+            'T' => Some(Turtle),
+            'P' => Some(Platino),
+            '+' => Some(Door),
+            _ => None,
+        }
+    }
+
+    /// Returns true for the level goals the player has to reach
+    pub fn is_objective(&self) -> bool {
+        matches!(self, Spawnables::TreasureChest)
+    }
+
+    /// Returns true for everything that moves around on its own
+    pub fn is_creature(&self) -> bool {
+        matches!(self, Spawnables::Turtle)
+    }
+
+    /// Returns true for spawns that block movement for good, as they neither move on their own nor
+    /// can be pushed. [`MapBuildData::reserve_spawn`] keeps them out of chokepoints.
+    pub fn is_immovable(&self) -> bool {
+        matches!(self, Spawnables::Platino)
+    }
 
-pub fn fill_room(rng: &mut MapRng, room: &Rect, max_spawns: u32) -> SpawnList {
-    let mut room_tiles = Vec::with_capacity((room.width() * room.height()) as usize);
-    for x in room.x1 + 1..room.x2 {
-        for y in room.y1 + 1..room.y2 {
-            room_tiles.push((x, y));
+    /// Returns how often these [`Spawnables`] may be placed on a single map by any modifier,
+    /// enforced by [`MapBuildData::reserve_spawn`]
+    pub fn max_per_map(&self) -> Option<u32> {
+        match self {
+            Spawnables::Platino => Some(1),
+            _ => None,
         }
     }
 
-    fill_region(rng, &room_tiles, max_spawns)
+    /// Returns the character representing these [`Spawnables`], the inverse of [`Self::from_glyph`]
+    pub fn glyph(&self) -> char {
+        use Spawnables::*;
+        match self {
+            TreasureChest => '$',
+            Turtle => 'T',
+            Platino => 'P',
+            Door => '+',
+        }
+    }
 }
 
-/// Randomly selects spawn points from a given list of valid spawn positions (`region`)
-pub fn fill_region(rng: &mut MapRng, region: &[(u32, u32)], max_spawns: u32) -> SpawnList {
-    let mut spawn_points = SpawnList::new();
-    if region.is_empty() {
-        return spawn_points;
-    }
-    let spawn_table = spawn_table();
-
-    {
-        let num_spawns = rng.gen_range(0..=max_spawns);
-
-        // Select spawn points from the given region
-        for _i in 0..num_spawns {
-            let mut added = false;
-            let mut tries = 0;
-            while !added && tries < 20 {
-                let idx = rng.gen_range(0..region.len());
-                let pos = region[idx];
-                if let Entry::Vacant(e) = spawn_points.entry(pos) {
-                    e.insert(spawn_table.roll(rng).unwrap());
-                    added = true;
-                } else {
-                    tries += 1;
+/// Number of levels after which each room or region may receive an additional spawn
+const LEVELS_PER_EXTRA_SPAWN: u32 = 3;
+
+/// Returns what to spawn into regions with the given tag, or into untagged regions and rooms.
+/// Turtles populate every level, between the third and tenth level a single Platino may show up
+/// among them (see [`Spawnables::max_per_map`]) wherever he does not block the way. Lairs are left to the turtles, while a treasure zone is where Platino is most likely found.
+pub fn spawn_table(tag: Option<RegionTag>) -> RandomTable<Spawnables> {
+    use Spawnables::*;
+    let platino = |weight| {
+        TableEntry::new(Platino, weight)
+            .with_min_depth(3)
+            .with_max_depth(10)
+            .with_weight_per_depth(1)
+    };
+    match tag {
+        None => RandomTable::new().add(Turtle, 10).add_entry(platino(1)),
+        Some(RegionTag::Lair) => RandomTable::new().add(Turtle, 10),
+        Some(RegionTag::TreasureZone) => RandomTable::new().add(Turtle, 10).add_entry(platino(5)),
+        Some(RegionTag::Quiet) => RandomTable::new(),
+    }
+}
+
+/// Randomly places spawns rolled from the `spawn_table` for the map's depth onto free tiles of the
+/// `region`. Up to `max_spawns` are placed on the first levels, with one more for every
+/// [`LEVELS_PER_EXTRA_SPAWN`] levels.
+pub fn fill_region(
+    rng: &mut MapRng,
+    build_data: &mut MapBuildData,
+    spawn_table: &RandomTable<Spawnables>,
+    region: &[(u32, u32)],
+    max_spawns: u32,
+) {
+    // Previous modifiers may have turned some tiles of the region into non-walkable ones or placed something there
+    let free_tiles: Vec<(u32, u32)> = region
+        .iter()
+        .copied()
+        .filter(|&pos| build_data.is_free(pos))
+        .collect();
+    if free_tiles.is_empty() {
+        return;
+    }
+    let depth = build_data.depth;
+    let max_spawns = max_spawns + (depth - 1) / LEVELS_PER_EXTRA_SPAWN;
+    let num_spawns = rng.gen_range(0..=max_spawns);
+
+    // Select spawn points from the given region
+    for _i in 0..num_spawns {
+        let mut tries = 0;
+        while tries < 20 {
+            let pos = free_tiles[rng.gen_range(0..free_tiles.len())];
+            if !build_data.is_free(pos) {
+                tries += 1;
+                continue;
+            }
+            let Some(spawn) = spawn_table.roll_at_depth(rng, depth) else {
+                return;
+            };
+            match build_data.reserve_spawn(pos, spawn) {
+                Ok(()) => break,
+                // Roll again, maybe something else or at another position
+                Err(SpawnError::LimitReached | SpawnError::Chokepoint) => tries += 1,
+                Err(SpawnError::Occupied) => {
+                    panic!("Spawn positions are selected among free tiles only!")
                 }
             }
         }
     }
-
-    spawn_points
 }
@@ -0,0 +1,222 @@
+use serde::Deserialize;
+
+use super::{region::Region, MapBuildData, MapModifier, MapRng, SpawnList};
+
+/// Mirror axes of a [`Symmetry`] modifier
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum SymmetryMode {
+    /// Mirrors the map along the vertical center line, i.e. left and right are swapped
+    Horizontal,
+    /// Mirrors the map along the horizontal center line, i.e. top and bottom are swapped
+    Vertical,
+    /// Mirrors the map along both center lines, resulting in four identical quarters
+    Both,
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+/// Makes the map symmetric by replacing one half of it with the mirror image of the other half.
+/// The half containing the starting position (or the left/bottom half if there is none) is kept.
+///
+/// Rooms, room links, regions and spawns of the kept half are mirrored along with the tiles. Rooms
+/// crossing the center line would no longer match their tiles and are removed from the metadata.
+/// Objectives and spawns that would exceed their per-map cap are not mirrored.
+/// Mirroring may disconnect both halves, so add a [`CullUnreachable`](super::cull_unreachable::CullUnreachable)
+/// modifier afterwards.
+pub struct Symmetry {
+    mode: SymmetryMode,
+}
+
+impl Symmetry {
+    pub fn new(mode: SymmetryMode) -> Box<Symmetry> {
+        Box::new(Symmetry { mode })
+    }
+}
+
+impl MapModifier for Symmetry {
+    fn modify_map(&mut self, _rng: &mut MapRng, build_data: &mut MapBuildData) {
+        if matches!(self.mode, SymmetryMode::Horizontal | SymmetryMode::Both) {
+            mirror(build_data, Axis::X);
+        }
+        if matches!(self.mode, SymmetryMode::Vertical | SymmetryMode::Both) {
+            mirror(build_data, Axis::Y);
+        }
+    }
+}
+
+/// Replaces the half of the map beyond the center line perpendicular to `axis` with the mirror image of the other half
+fn mirror(build_data: &mut MapBuildData, axis: Axis) {
+    let size = match axis {
+        Axis::X => build_data.map.width,
+        Axis::Y => build_data.map.height,
+    };
+    let coord = |(x, y): (u32, u32)| match axis {
+        Axis::X => x,
+        Axis::Y => y,
+    };
+    let reflect = |(x, y): (u32, u32)| match axis {
+        Axis::X => (size - 1 - x, y),
+        Axis::Y => (x, size - 1 - y),
+    };
+    let keep_high = build_data
+        .metadata
+        .starting_position
+        .is_some_and(|p| coord(p) > coord(reflect(p)));
+    // Tiles on the center line are part of both halves
+    let is_kept = |p: (u32, u32)| {
+        if keep_high {
+            coord(p) >= coord(reflect(p))
+        } else {
+            coord(p) <= coord(reflect(p))
+        }
+    };
+
+    let map = &mut build_data.map;
+    for idx in 0..map.length() {
+        let pos = map.idx_to_xy(idx).unwrap();
+        if !is_kept(pos) {
+            let (x, y) = reflect(pos);
+            map.tiles[idx] = map.tiles[map.xy_to_idx(x, y).unwrap()];
+        }
+    }
+
+    let kept: SpawnList = build_data
+        .metadata
+        .spawn_list
+        .drain()
+        .filter(|&(pos, _)| is_kept(pos))
+        .collect();
+    build_data.metadata.spawn_list = kept.clone();
+    // Mirror images must respect the spawn caps, and the map keeps its single objective
+    for (pos, spawn) in kept {
+        if !spawn.is_objective() {
+            let _ = build_data.reserve_spawn(reflect(pos), spawn);
+        }
+    }
+
+    let metadata = &mut build_data.metadata;
+    if let Some(rooms) = metadata.rooms.take() {
+        // Each kept room is directly followed by its mirror image
+        let mut new_index = vec![None; rooms.len()];
+        let mut mirrored_rooms = Vec::with_capacity(2 * rooms.len());
+        for (i, room) in rooms.into_iter().enumerate() {
+            if room.tiles().into_iter().all(is_kept) {
+                new_index[i] = Some(mirrored_rooms.len());
+                let mirrored = match axis {
+                    Axis::X => room.mirrored_horizontally(size),
+                    Axis::Y => room.mirrored_vertically(size),
+                };
+                mirrored_rooms.push(room);
+                mirrored_rooms.push(mirrored);
+            }
+        }
+        metadata.rooms = Some(mirrored_rooms);
+        if let Some(links) = metadata.room_links.as_mut() {
+            *links = links
+                .iter()
+                .filter_map(|&(a, b)| Some((new_index[a]?, new_index[b]?)))
+                .flat_map(|(a, b)| [(a, b), (a + 1, b + 1)])
+                .collect();
+        }
+    }
+
+    if let Some(regions) = metadata.regions.take() {
+        // Mirror images keep the tags of their original region
+        let mut mirrored_regions = Vec::with_capacity(2 * regions.len());
+        for region in regions {
+            let kept: Vec<(u32, u32)> = region
+                .tiles()
+                .iter()
+                .copied()
+                .filter(|&p| is_kept(p))
+                .collect();
+            if kept.is_empty() {
+                continue;
+            }
+            let mirrored: Vec<(u32, u32)> = kept
+                .iter()
+                .map(|&p| reflect(p))
+                .filter(|&p| !is_kept(p))
+                .collect();
+            // Regions touching the center line stay connected to their mirror image
+            let touches_center = kept
+                .iter()
+                .any(|&p| coord(p).abs_diff(coord(reflect(p))) <= 1);
+            if touches_center {
+                mirrored_regions.push((kept.into_iter().chain(mirrored).collect(), region.tags));
+            } else {
+                mirrored_regions.push((kept, region.tags.clone()));
+                mirrored_regions.push((mirrored, region.tags));
+            }
+        }
+        let (tile_sets, tags): (Vec<_>, Vec<_>) = mirrored_regions.into_iter().unzip();
+        let mut regions = Region::from_tile_sets(tile_sets);
+        for (region, tags) in regions.iter_mut().zip(tags) {
+            region.tags = tags;
+        }
+        metadata.regions = Some(regions);
+    }
+    build_data.take_snapshot();
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::map::TileType;
+    use crate::map_builder::{region::RegionTag, spawner::Spawnables};
+
+    #[test]
+    fn test_map_is_symmetric() {
+        let mut build_data = MapBuildData::new(9, 6);
+        for (x, y) in [(1, 1), (2, 1), (2, 2), (6, 4)] {
+            let idx = build_data.map.xy_to_idx(x, y).unwrap();
+            build_data.map.tiles[idx] = TileType::Floor;
+        }
+        build_data
+            .metadata
+            .spawn_list
+            .insert((2, 2), Spawnables::Turtle);
+        build_data
+            .metadata
+            .spawn_list
+            .insert((1, 1), Spawnables::TreasureChest);
+        let mut regions = Region::from_tile_sets([vec![(1, 1), (2, 1), (2, 2)], vec![(6, 4)]]);
+        regions[0].tags.insert(RegionTag::Lair);
+        build_data.metadata.regions = Some(regions);
+
+        let mut rng = MapRng::seed_from_u64(0);
+        Symmetry::new(SymmetryMode::Both).modify_map(&mut rng, &mut build_data);
+
+        let map = &build_data.map;
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let tile = map.tiles[map.xy_to_idx(x, y).unwrap()];
+                let mirror_x = map.tiles[map.xy_to_idx(map.width - 1 - x, y).unwrap()];
+                let mirror_y = map.tiles[map.xy_to_idx(x, map.height - 1 - y).unwrap()];
+                assert_eq!(tile, mirror_x);
+                assert_eq!(tile, mirror_y);
+            }
+        }
+        assert_eq!(
+            map.tiles.iter().filter(|&&t| t == TileType::Floor).count(),
+            12
+        );
+        let mut spawns: Vec<_> = build_data.metadata.spawn_list.keys().copied().collect();
+        spawns.sort();
+        assert_eq!(spawns, [(1, 1), (2, 2), (2, 3), (6, 2), (6, 3)]);
+        let objectives = build_data.metadata.spawn_list.values();
+        assert_eq!(objectives.filter(|s| s.is_objective()).count(), 1);
+        // The second region has been overwritten. The first one is mirrored into a separate region
+        // horizontally, but touches the horizontal center line and merges with its mirror image.
+        let regions = build_data.metadata.regions.unwrap();
+        assert_eq!(regions.len(), 2);
+        assert!(regions.iter().all(|region| region.tiles().len() == 6));
+        assert!(regions.iter().all(|region| region.has_tag(RegionTag::Lair)));
+    }
+}
@@ -0,0 +1,173 @@
+use serde::Deserialize;
+use std::{fmt, ops::RangeInclusive};
+
+use super::MapBuildData;
+
+/// Properties a finished map needs to have to be playable, checked by [`BuilderChain::build_map`](super::BuilderChain::build_map)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MapRequirements {
+    /// Minimum fraction of walkable tiles
+    pub min_floor_ratio: f32,
+    /// Minimum number of rooms, only checked for maps with rooms
+    pub min_rooms: usize,
+    /// Minimum path length from the starting position to each objective
+    pub min_objective_distance: u32,
+    /// Allowed number of creatures in the spawn list
+    pub creatures: RangeInclusive<usize>,
+}
+
+impl Default for MapRequirements {
+    fn default() -> Self {
+        MapRequirements {
+            min_floor_ratio: 0.1,
+            min_rooms: 2,
+            min_objective_distance: 10,
+            creatures: 1..=100,
+        }
+    }
+}
+
+/// The first requirement a map does not meet
+#[derive(Debug, PartialEq)]
+pub enum InvalidMapError {
+    NotEnoughFloor(f32),
+    NotEnoughRooms(usize),
+    MissingStartingPosition,
+    MissingObjective,
+    UnreachableObjective((u32, u32)),
+    ObjectiveTooClose((u32, u32), u32),
+    CreatureCount(usize),
+}
+
+impl fmt::Display for InvalidMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidMapError::NotEnoughFloor(ratio) => {
+                write!(f, "only {:.1}% of the tiles are walkable", 100.0 * ratio)
+            }
+            InvalidMapError::NotEnoughRooms(rooms) => write!(f, "only {rooms} rooms"),
+            InvalidMapError::MissingStartingPosition => write!(f, "no starting position"),
+            InvalidMapError::MissingObjective => write!(f, "no objective"),
+            InvalidMapError::UnreachableObjective(pos) => {
+                write!(f, "the objective at {pos:?} cannot be reached")
+            }
+            InvalidMapError::ObjectiveTooClose(pos, distance) => {
+                write!(f, "the objective at {pos:?} is only {distance} steps away")
+            }
+            InvalidMapError::CreatureCount(count) => write!(f, "{count} creatures"),
+        }
+    }
+}
+
+impl MapRequirements {
+    /// Checks all requirements in the order of the [`InvalidMapError`] variants
+    pub fn validate(&self, build_data: &MapBuildData) -> Result<(), InvalidMapError> {
+        let map = &build_data.map;
+        let metadata = &build_data.metadata;
+
+        let walkable = map.tiles.iter().filter(|t| t.is_walkable()).count();
+        let floor_ratio = walkable as f32 / map.length() as f32;
+        if floor_ratio < self.min_floor_ratio {
+            return Err(InvalidMapError::NotEnoughFloor(floor_ratio));
+        }
+        if let Some(rooms) = &metadata.rooms {
+            if rooms.len() < self.min_rooms {
+                return Err(InvalidMapError::NotEnoughRooms(rooms.len()));
+            }
+        }
+
+        let start = metadata
+            .starting_position
+            .ok_or(InvalidMapError::MissingStartingPosition)?;
+        let path_lengths = map.path_lengths(start);
+        let mut objectives: Vec<(u32, u32)> = metadata
+            .spawn_list
+            .iter()
+            .filter(|(_, spawn)| spawn.is_objective())
+            .map(|(&pos, _)| pos)
+            .collect();
+        if objectives.is_empty() {
+            return Err(InvalidMapError::MissingObjective);
+        }
+        // Report the same objective regardless of the spawn list's order
+        objectives.sort();
+        for pos in objectives {
+            match path_lengths.get(&pos) {
+                None => return Err(InvalidMapError::UnreachableObjective(pos)),
+                Some(&distance) if distance < self.min_objective_distance => {
+                    return Err(InvalidMapError::ObjectiveTooClose(pos, distance));
+                }
+                Some(_) => {}
+            }
+        }
+
+        let creatures = metadata
+            .spawn_list
+            .values()
+            .filter(|spawn| spawn.is_creature())
+            .count();
+        if !self.creatures.contains(&creatures) {
+            return Err(InvalidMapError::CreatureCount(creatures));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+    use crate::map_builder::spawner::Spawnables;
+
+    #[test]
+    fn test_validate() {
+        // A corridor along the bottom row and a separate tile in the top row
+        let mut build_data = MapBuildData::new(20, 3);
+        for idx in (0..20).chain([45]) {
+            build_data.map.tiles[idx] = TileType::Floor;
+        }
+        let requirements = MapRequirements::default();
+        let spawn = |build_data: &mut MapBuildData, pos, spawn| {
+            build_data.reserve_spawn(pos, spawn).unwrap();
+        };
+
+        assert_eq!(
+            requirements.validate(&build_data),
+            Err(InvalidMapError::MissingStartingPosition)
+        );
+        build_data.reserve_starting_position((0, 0)).unwrap();
+        assert_eq!(
+            requirements.validate(&build_data),
+            Err(InvalidMapError::MissingObjective)
+        );
+        spawn(&mut build_data, (5, 2), Spawnables::TreasureChest);
+        assert_eq!(
+            requirements.validate(&build_data),
+            Err(InvalidMapError::UnreachableObjective((5, 2)))
+        );
+        build_data.metadata.spawn_list.clear();
+        spawn(&mut build_data, (5, 0), Spawnables::TreasureChest);
+        assert_eq!(
+            requirements.validate(&build_data),
+            Err(InvalidMapError::ObjectiveTooClose((5, 0), 5))
+        );
+        build_data.metadata.spawn_list.clear();
+        spawn(&mut build_data, (15, 0), Spawnables::TreasureChest);
+        assert_eq!(
+            requirements.validate(&build_data),
+            Err(InvalidMapError::CreatureCount(0))
+        );
+        spawn(&mut build_data, (10, 0), Spawnables::Turtle);
+        assert_eq!(requirements.validate(&build_data), Ok(()));
+
+        let strict = MapRequirements {
+            min_floor_ratio: 0.5,
+            ..Default::default()
+        };
+        assert!(matches!(
+            strict.validate(&build_data),
+            Err(InvalidMapError::NotEnoughFloor(_))
+        ));
+    }
+}
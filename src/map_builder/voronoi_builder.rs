@@ -0,0 +1,149 @@
+use rand::Rng;
+use std::collections::BTreeMap;
+
+use super::{
+    region::Region, region_based_builders::DistanceFunction, InitialMapBuilder, MapBuildData,
+    MapRng,
+};
+use crate::map::TileType;
+
+/// Builds a 'hive' of Voronoi cells around randomly selected seed points: walls are placed on the
+/// cell boundaries and a single opening connects each pair of neighboring cells. Records the floor
+/// tiles of each cell as a region in [`MapMetadata::regions`](super::MapMetadata::regions).
+pub struct VoronoiCellBuilder {
+    number_of_cells: u32,
+    distance_function: DistanceFunction,
+}
+
+impl VoronoiCellBuilder {
+    pub fn new(
+        number_of_cells: u32,
+        distance_function: DistanceFunction,
+    ) -> Box<VoronoiCellBuilder> {
+        Box::new(VoronoiCellBuilder {
+            number_of_cells,
+            distance_function,
+        })
+    }
+}
+
+impl InitialMapBuilder for VoronoiCellBuilder {
+    fn build_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let (width, height) = (build_data.map.width, build_data.map.height);
+        if width < 3 || height < 3 {
+            return;
+        }
+
+        // Use distinct seed points to avoid empty cells
+        let interior = ((width - 2) * (height - 2)) as usize;
+        let mut seeds: Vec<(u32, u32)> = Vec::with_capacity(self.number_of_cells as usize);
+        while seeds.len() < (self.number_of_cells as usize).min(interior) {
+            let seed = (rng.gen_range(1..width - 1), rng.gen_range(1..height - 1));
+            if !seeds.contains(&seed) {
+                seeds.push(seed);
+            }
+        }
+
+        // Determine which cell each interior tile belongs to
+        let map = &build_data.map;
+        let mut owner = vec![None; map.length()];
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let idx = map.xy_to_idx(x, y).unwrap();
+                owner[idx] = Some(self.distance_function.find_closest_point((x, y), &seeds));
+            }
+        }
+
+        // Tiles bordering a different cell to the right or above (including diagonally) become walls,
+        // which keeps the boundaries thin but without any diagonal gaps
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let map = &build_data.map;
+                let idx = map.xy_to_idx(x, y).unwrap();
+                let on_boundary = [(x + 1, y), (x - 1, y + 1), (x, y + 1), (x + 1, y + 1)]
+                    .into_iter()
+                    .map(|(nx, ny)| owner[map.xy_to_idx(nx, ny).unwrap()])
+                    .any(|n| n.is_some() && n != owner[idx]);
+                if !on_boundary {
+                    build_data.map.tiles[idx] = TileType::Floor;
+                }
+            }
+        }
+        build_data.take_snapshot();
+
+        // Collect all boundary tiles that would connect floor tiles of two neighboring cells.
+        // A BTreeMap keeps the iteration order (and hence the result) deterministic.
+        let map = &build_data.map;
+        let mut openings: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let idx = map.xy_to_idx(x, y).unwrap();
+                if map.tiles[idx] != TileType::Wall {
+                    continue;
+                }
+                let floor_owners: Vec<usize> = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                    .into_iter()
+                    .map(|(nx, ny)| map.xy_to_idx(nx, ny).unwrap())
+                    .filter(|&n| map.tiles[n] == TileType::Floor)
+                    .filter_map(|n| owner[n])
+                    .collect();
+                for (i, &a) in floor_owners.iter().enumerate() {
+                    for &b in floor_owners[i + 1..].iter() {
+                        if a != b {
+                            openings.entry((a.min(b), a.max(b))).or_default().push(idx);
+                        }
+                    }
+                }
+            }
+        }
+        for candidates in openings.values() {
+            let idx = candidates[rng.gen_range(0..candidates.len())];
+            build_data.map.tiles[idx] = TileType::Floor;
+        }
+        build_data.take_snapshot();
+
+        let mut cells = vec![Vec::new(); seeds.len()];
+        for (idx, tile) in build_data.map.tiles.iter().enumerate() {
+            if let (TileType::Floor, Some(cell)) = (tile, owner[idx]) {
+                cells[cell].push(build_data.map.idx_to_xy(idx).unwrap());
+            }
+        }
+        build_data.metadata.regions = Some(Region::from_tile_sets(cells));
+        build_data.take_snapshot();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_floor_tiles_belong_to_exactly_one_region() {
+        for distance_function in [
+            DistanceFunction::Manhattan,
+            DistanceFunction::Euclidean,
+            DistanceFunction::Maximum,
+        ] {
+            for seed in 0..10 {
+                let mut rng = MapRng::seed_from_u64(seed);
+                let mut build_data = MapBuildData::new(60, 40);
+                VoronoiCellBuilder::new(24, distance_function).build_map(&mut rng, &mut build_data);
+
+                let map = &build_data.map;
+                let regions = build_data.metadata.regions.as_ref().unwrap();
+                assert!(regions.iter().all(|r| !r.tiles().is_empty()));
+                for idx in 0..map.length() {
+                    let pos = map.idx_to_xy(idx).unwrap();
+                    let owners = regions.iter().filter(|r| r.tiles().contains(&pos)).count();
+                    let expected = usize::from(map.tiles[idx] == TileType::Floor);
+                    assert_eq!(
+                        owners, expected,
+                        "{distance_function:?} seed {seed} {pos:?}"
+                    );
+                }
+            }
+        }
+    }
+}
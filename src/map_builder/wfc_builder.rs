@@ -0,0 +1,453 @@
+use bevy::log::*;
+use rand::Rng;
+use std::collections::HashMap;
+
+use super::{InitialMapBuilder, MapBuildData, MapRng};
+use crate::map::{GameMap, TileType};
+
+/// Number of restarts after running into a contradiction before giving up
+const MAX_ATTEMPTS: u32 = 10;
+
+/// Number of collapsed cells between two snapshots
+const SNAPSHOT_INTERVAL: usize = 250;
+
+/// Offsets to the four orthogonal neighbors of a cell, opposite directions are stored at `d ^ 1`
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Provides the sample map from which the [`WaveFunctionCollapseBuilder`] learns its tile patterns
+pub enum WfcSource {
+    /// Runs another builder on a map of the same size and learns from its result
+    Builder(Box<dyn InitialMapBuilder>),
+    /// Parses a hand-written sample with `#` marking walls and `.` marking floor tiles
+    Sample(String),
+}
+
+/// Synthesizes a new map with the overlapping wave function collapse algorithm: all N x N tile
+/// patterns of a sample map are learned and then stitched together such that overlapping patterns agree.
+///
+/// Contradictions are resolved by restarting with the same [`MapRng`], so the result is still
+/// deterministic for a given seed. If no attempt succeeds, the sample map is used if it has the
+/// correct size (otherwise the map is left untouched).
+pub struct WaveFunctionCollapseBuilder {
+    source: WfcSource,
+    pattern_size: u32,
+}
+
+impl WaveFunctionCollapseBuilder {
+    pub fn new(source: WfcSource, pattern_size: u32) -> Box<WaveFunctionCollapseBuilder> {
+        Box::new(WaveFunctionCollapseBuilder {
+            source,
+            pattern_size,
+        })
+    }
+
+    /// Determines the sample map, building it first if necessary
+    fn sample(
+        &mut self,
+        rng: &mut MapRng,
+        build_data: &mut MapBuildData,
+    ) -> Result<GameMap, String> {
+        match &mut self.source {
+            WfcSource::Builder(builder) => {
+                let mut sample_data =
+                    MapBuildData::new(build_data.map.width, build_data.map.height);
+                builder.build_map(rng, &mut sample_data);
+                // Keep the history of the sample to see where the patterns came from
+                build_data.history.append(&mut sample_data.history);
+                Ok(sample_data.map)
+            }
+            WfcSource::Sample(text) => parse_sample(text),
+        }
+    }
+}
+
+impl InitialMapBuilder for WaveFunctionCollapseBuilder {
+    fn build_map(&mut self, rng: &mut MapRng, build_data: &mut MapBuildData) {
+        let sample = match self.sample(rng, build_data) {
+            Ok(sample) => sample,
+            Err(e) => {
+                warn!("Cannot use the sample map: {e}");
+                return;
+            }
+        };
+        let patterns = Patterns::learn(&sample, self.pattern_size);
+        if patterns.tiles.is_empty() {
+            warn!(
+                "Sample map ({} x {}) is smaller than the pattern size {}!",
+                sample.width, sample.height, self.pattern_size
+            );
+            return;
+        }
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let mut wave = Wave::new(&patterns, &build_data.map);
+            match wave.run(rng, build_data) {
+                Ok(()) => {
+                    wave.render(&mut build_data.map);
+                    // Keep the map enclosed by walls
+                    let (width, height) = (build_data.map.width, build_data.map.height);
+                    for (idx, tile) in build_data.map.tiles.iter_mut().enumerate() {
+                        let (x, y) = (idx as u32 % width, idx as u32 / width);
+                        if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                            *tile = TileType::Wall;
+                        }
+                    }
+                    build_data.take_snapshot();
+                    return;
+                }
+                Err(Contradiction) => {
+                    debug!("Wave function collapse ran into a contradiction in attempt {attempt}");
+                }
+            }
+        }
+
+        warn!("Wave function collapse failed after {MAX_ATTEMPTS} attempts!");
+        if sample.width == build_data.map.width && sample.height == build_data.map.height {
+            build_data.map.tiles = sample.tiles;
+        }
+        build_data.take_snapshot();
+    }
+}
+
+/// Creates a [`GameMap`] from a text sample with one line per row of tiles
+pub(super) fn parse_sample(text: &str) -> Result<GameMap, String> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+    let height = lines.len() as u32;
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u32;
+    if width == 0 || height == 0 {
+        return Err("the sample map is empty".to_string());
+    }
+    let mut map = GameMap::new(width, height);
+    for (y, line) in lines.iter().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            let idx = map.xy_to_idx(x as u32, y as u32).unwrap();
+            map.tiles[idx] = match c {
+                '#' => TileType::Wall,
+                '.' => TileType::Floor,
+                _ => {
+                    return Err(format!(
+                        "unknown tile '{c}' in the sample map at ({x}, {y})"
+                    ))
+                }
+            };
+        }
+    }
+    Ok(map)
+}
+
+/// All distinct N x N tile patterns of a sample map with their frequencies and compatibility rules
+struct Patterns {
+    size: u32,
+    /// Tiles of each pattern in row-major order
+    tiles: Vec<Vec<TileType>>,
+    /// How often each pattern occurs in the sample
+    weights: Vec<u32>,
+    /// `compatible[p][d]` lists all patterns that may be placed next to pattern `p` in direction `d`
+    compatible: Vec<[Vec<usize>; 4]>,
+}
+
+impl Patterns {
+    /// Extracts all patterns of the sample, wrapping around its edges. Treating the sample as periodic
+    /// ensures that every pattern can be continued in all directions which avoids most contradictions.
+    fn learn(sample: &GameMap, size: u32) -> Self {
+        let mut tiles: Vec<Vec<TileType>> = Vec::new();
+        let mut weights = Vec::new();
+        // Map patterns to their index to keep the order of discovery (and hence the result) deterministic
+        let mut index = HashMap::new();
+
+        if size > 0 && size <= sample.width && size <= sample.height {
+            for y in 0..sample.height {
+                for x in 0..sample.width {
+                    let pattern: Vec<TileType> = (0..size)
+                        .flat_map(|v| (0..size).map(move |u| (x + u, y + v)))
+                        .map(|(px, py)| {
+                            let idx = sample
+                                .xy_to_idx(px % sample.width, py % sample.height)
+                                .unwrap();
+                            sample.tiles[idx]
+                        })
+                        .collect();
+                    if let Some(&p) = index.get(&pattern) {
+                        weights[p] += 1;
+                    } else {
+                        index.insert(pattern.clone(), tiles.len());
+                        tiles.push(pattern);
+                        weights.push(1);
+                    }
+                }
+            }
+        }
+
+        let mut patterns = Patterns {
+            size,
+            tiles,
+            weights,
+            compatible: Vec::new(),
+        };
+        patterns.compatible = (0..patterns.tiles.len())
+            .map(|p| {
+                DIRECTIONS.map(|(dx, dy)| {
+                    (0..patterns.tiles.len())
+                        .filter(|&q| patterns.agree(p, q, dx, dy))
+                        .collect()
+                })
+            })
+            .collect();
+        patterns
+    }
+
+    /// Checks if pattern `q` placed at an offset of (dx, dy) from pattern `p` agrees with it where both overlap
+    fn agree(&self, p: usize, q: usize, dx: i32, dy: i32) -> bool {
+        let n = self.size as i32;
+        for y in dy.max(0)..(n + dy).min(n) {
+            for x in dx.max(0)..(n + dx).min(n) {
+                let p_tile = self.tiles[p][(y * n + x) as usize];
+                let q_tile = self.tiles[q][((y - dy) * n + (x - dx)) as usize];
+                if p_tile != q_tile {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Signals that at least one cell of the [`Wave`] cannot hold any pattern anymore
+struct Contradiction;
+
+/// Superposition of all patterns that are still possible for each cell of the output map. Each cell
+/// corresponds to the pattern whose top left corner is placed at the cell's position.
+struct Wave<'a> {
+    patterns: &'a Patterns,
+    width: u32,
+    height: u32,
+    /// Whether pattern `p` is still possible in cell `c`, stored at `c * num_patterns + p`
+    possible: Vec<bool>,
+    /// Number of patterns that are still possible per cell
+    remaining: Vec<usize>,
+    /// Number of patterns in the neighboring cell in direction `-d` that still allow pattern `p` in cell `c`,
+    /// stored at `c * num_patterns + p`
+    support: Vec<[usize; 4]>,
+    /// Pending (cell, pattern) pairs that have been ruled out but whose consequences have not been propagated
+    banned: Vec<(usize, usize)>,
+}
+
+impl<'a> Wave<'a> {
+    fn new(patterns: &'a Patterns, map: &GameMap) -> Self {
+        let width = (map.width + 1).saturating_sub(patterns.size).max(1);
+        let height = (map.height + 1).saturating_sub(patterns.size).max(1);
+        let num_patterns = patterns.tiles.len();
+        let num_cells = (width * height) as usize;
+
+        let initial_support: Vec<[usize; 4]> = (0..num_patterns)
+            .map(|p| [0, 1, 2, 3].map(|d| patterns.compatible[p][d ^ 1].len()))
+            .collect();
+
+        Wave {
+            patterns,
+            width,
+            height,
+            possible: vec![true; num_cells * num_patterns],
+            remaining: vec![num_patterns; num_cells],
+            support: (0..num_cells)
+                .flat_map(|_| initial_support.iter().copied())
+                .collect(),
+            banned: Vec::new(),
+        }
+    }
+
+    /// Collapses cells one by one until all cells are decided or a [`Contradiction`] is found
+    fn run(
+        &mut self,
+        rng: &mut MapRng,
+        build_data: &mut MapBuildData,
+    ) -> Result<(), Contradiction> {
+        let mut collapsed = 0;
+        while let Some(cell) = self.lowest_entropy_cell(rng)? {
+            self.collapse(rng, cell);
+            self.propagate()?;
+
+            collapsed += 1;
+            if collapsed % SNAPSHOT_INTERVAL == 0 {
+                self.render(&mut build_data.map);
+                build_data.take_snapshot();
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds an undecided cell with the fewest remaining patterns (breaking ties randomly)
+    fn lowest_entropy_cell(&self, rng: &mut MapRng) -> Result<Option<usize>, Contradiction> {
+        let mut min_remaining = usize::MAX;
+        let mut candidates = Vec::new();
+        for (cell, &remaining) in self.remaining.iter().enumerate() {
+            match remaining {
+                0 => return Err(Contradiction),
+                1 => {}
+                r if r < min_remaining => {
+                    min_remaining = r;
+                    candidates.clear();
+                    candidates.push(cell);
+                }
+                r if r == min_remaining => candidates.push(cell),
+                _ => {}
+            }
+        }
+        if candidates.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(candidates[rng.gen_range(0..candidates.len())]))
+        }
+    }
+
+    /// Selects one of the remaining patterns of a cell (weighted by frequency) and bans all others
+    fn collapse(&mut self, rng: &mut MapRng, cell: usize) {
+        let num_patterns = self.patterns.tiles.len();
+        let offset = cell * num_patterns;
+        let total: u32 = (0..num_patterns)
+            .filter(|&p| self.possible[offset + p])
+            .map(|p| self.patterns.weights[p])
+            .sum();
+        let mut roll = rng.gen_range(0..total);
+        let mut chosen = None;
+        for p in (0..num_patterns).filter(|&p| self.possible[offset + p]) {
+            if roll < self.patterns.weights[p] {
+                chosen = Some(p);
+                break;
+            }
+            roll -= self.patterns.weights[p];
+        }
+        let chosen = chosen.expect("Roll must select one of the remaining patterns");
+        for p in 0..num_patterns {
+            if p != chosen && self.possible[offset + p] {
+                self.ban(cell, p);
+            }
+        }
+    }
+
+    fn ban(&mut self, cell: usize, pattern: usize) {
+        let num_patterns = self.patterns.tiles.len();
+        self.possible[cell * num_patterns + pattern] = false;
+        self.remaining[cell] -= 1;
+        self.banned.push((cell, pattern));
+    }
+
+    /// Removes all patterns from neighboring cells that lost their last supporting pattern
+    fn propagate(&mut self) -> Result<(), Contradiction> {
+        let num_patterns = self.patterns.tiles.len();
+        while let Some((cell, pattern)) = self.banned.pop() {
+            let (x, y) = (cell as u32 % self.width, cell as u32 / self.width);
+            for (d, (dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if nx < 0 || ny < 0 || nx >= self.width as i32 || ny >= self.height as i32 {
+                    continue;
+                }
+                let neighbor = (ny as u32 * self.width + nx as u32) as usize;
+                for &q in self.patterns.compatible[pattern][d].iter() {
+                    let idx = neighbor * num_patterns + q;
+                    self.support[idx][d] -= 1;
+                    if self.support[idx][d] == 0 && self.possible[idx] {
+                        self.ban(neighbor, q);
+                        if self.remaining[neighbor] == 0 {
+                            self.banned.clear();
+                            return Err(Contradiction);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the tiles of all decided cells to the map, undecided tiles are shown as walls
+    fn render(&self, map: &mut GameMap) {
+        let num_patterns = self.patterns.tiles.len();
+        let n = self.patterns.size;
+        for tile in map.tiles.iter_mut() {
+            *tile = TileType::Wall;
+        }
+        for cell in 0..self.remaining.len() {
+            if self.remaining[cell] != 1 {
+                continue;
+            }
+            let offset = cell * num_patterns;
+            let pattern = (0..num_patterns)
+                .find(|&p| self.possible[offset + p])
+                .unwrap();
+            let (x, y) = (cell as u32 % self.width, cell as u32 / self.width);
+            for v in 0..n {
+                for u in 0..n {
+                    if let Ok(idx) = map.xy_to_idx(x + u, y + v) {
+                        map.tiles[idx] = self.patterns.tiles[pattern][(v * n + u) as usize];
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const SAMPLE: &str = "
+        ##########
+        #....#...#
+        #....#...#
+        #........#
+        ###.######
+        #........#
+        #.##.....#
+        ##########";
+
+    fn build(seed: u64) -> GameMap {
+        let mut rng = MapRng::seed_from_u64(seed);
+        let mut build_data = MapBuildData::new(30, 20);
+        WaveFunctionCollapseBuilder::new(WfcSource::Sample(SAMPLE.to_string()), 3)
+            .build_map(&mut rng, &mut build_data);
+        build_data.map
+    }
+
+    #[test]
+    fn test_parse_sample() {
+        let map = parse_sample(SAMPLE).unwrap();
+        assert_eq!((map.width, map.height), (10, 8));
+        assert_eq!(map.tiles[map.xy_to_idx(1, 1).unwrap()], TileType::Floor);
+        assert_eq!(map.tiles[map.xy_to_idx(5, 1).unwrap()], TileType::Wall);
+
+        assert!(parse_sample("#####\n#.x.#\n#####").is_err());
+        assert!(parse_sample("").is_err());
+        assert!(parse_sample("  \n  ").is_err());
+    }
+
+    #[test]
+    fn test_output_is_deterministic() {
+        assert_eq!(build(7).tiles, build(7).tiles);
+    }
+
+    #[test]
+    fn test_output_only_contains_learned_patterns() {
+        let sample = parse_sample(SAMPLE).unwrap();
+        let learned = Patterns::learn(&sample, 3);
+        let map = build(3);
+        assert!(map.tiles.contains(&TileType::Floor));
+        // Skip the outermost tiles which are always turned into walls
+        for y in 1..map.height - 3 {
+            for x in 1..map.width - 3 {
+                let pattern: Vec<TileType> = (0..3)
+                    .flat_map(|v| (0..3).map(move |u| (x + u, y + v)))
+                    .map(|(px, py)| map.tiles[map.xy_to_idx(px, py).unwrap()])
+                    .collect();
+                assert!(
+                    learned.tiles.contains(&pattern),
+                    "Unknown pattern {pattern:?}"
+                );
+            }
+        }
+    }
+}
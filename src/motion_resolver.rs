@@ -1,9 +1,6 @@
 use bevy::{prelude::*, utils::HashMap};
 
-use crate::{
-    components::Position,
-    map::{GameMap, TileType},
-};
+use crate::{components::Position, map::GameMap};
 
 /// Used to resolve [`MoveAttempt`]s and push blocking entities in the direction of motion.
 #[derive(Debug, Default)]
@@ -98,10 +95,10 @@ impl MoveAttempt {
         if let (Ok(from_idx), Ok(to_idx)) =
             (map.xy_to_idx(from.x, from.y), map.xy_to_idx(to.x, to.y))
         {
-            if map.tiles[to_idx] == TileType::Wall {
+            if !map.tiles[to_idx].is_walkable() {
                 return MoveStatus::Illegal;
             }
-            if map.tile_content[from_idx].iter().any(|&e| e == self.entity) {
+            if map.tile_content[from_idx].contains(&self.entity) {
                 if let Some(e) = map.blocked_by[to_idx] {
                     MoveStatus::RequiresPush(e)
                 } else {
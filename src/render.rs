@@ -1,9 +1,10 @@
 use bevy::{prelude::*, render::camera::Camera2d};
 
 use crate::{
-    components::{Position, Viewshed},
+    components::{Door, Position, Viewshed},
     map::{GameMap, TileType},
     player::Player,
+    spawner::get_texture_atlas_handle,
 };
 
 /// Bundles systems responsible for rendering
@@ -16,7 +17,8 @@ impl Plugin for RenderPlugin {
             .add_system(place_characters)
             .add_system(player_follow_camera)
             .add_system(render_map)
-            .add_system(render_creatures);
+            .add_system(render_creatures)
+            .add_system(render_doors);
     }
 }
 
@@ -90,11 +92,24 @@ pub fn render_creatures(
 ) {
     for view in view.iter() {
         for (mut visible, p) in objects.iter_mut() {
-            if view.visible_tiles.contains(p) {
-                visible.is_visible = true;
-            } else {
-                visible.is_visible = false;
-            }
+            visible.is_visible = view.visible_tiles.contains(p);
         }
     }
 }
+
+/// Switches the sprites of [`Door`s](Door) that have been opened or closed
+fn render_doors(
+    mut doors: Query<(&Door, &mut Handle<TextureAtlas>), Changed<Door>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    for (door, mut atlas) in doors.iter_mut() {
+        // Both spritesheets share the same layout, the open doors are in the second one
+        let spritesheet = if door.open {
+            "Dawnlike/Objects/Door1.png"
+        } else {
+            "Dawnlike/Objects/Door0.png"
+        };
+        *atlas = get_texture_atlas_handle(spritesheet, 8, 6, &asset_server, &mut texture_atlases);
+    }
+}
@@ -2,7 +2,9 @@ use bevy::prelude::*;
 use iyes_loopless::prelude::AppLooplessStateExt;
 
 use crate::{
-    components::{Actor, BlocksMovement, LevelGoal, Monster, Position, Pushable, Viewshed},
+    components::{
+        Actor, BlocksMovement, BlocksVision, Door, LevelGoal, Monster, Position, Pushable, Viewshed,
+    },
     map::{GameMap, TileType},
     map_builder::{spawner::Spawnables, MapMetadata},
     player::Player,
@@ -48,7 +50,7 @@ fn setup_player(
         .insert(Player::new(100))
         // The player position will be set upon map generation based on the starting position
         .insert(Viewshed::new(7))
-        .insert(Actor::default())
+        .insert(Actor)
         .insert(BlocksMovement);
 }
 
@@ -75,6 +77,18 @@ fn spawn_things(
                 asset_server.as_ref(),
                 texture_atlases.as_mut(),
             ),
+            Platino => platino(
+                Position::new(*x, *y),
+                &mut commands,
+                asset_server.as_ref(),
+                texture_atlases.as_mut(),
+            ),
+            Door => door(
+                Position::new(*x, *y),
+                &mut commands,
+                asset_server.as_ref(),
+                texture_atlases.as_mut(),
+            ),
         }
     }
 
@@ -133,13 +147,63 @@ fn turtle(
         .insert(Monster)
         .insert(pos)
         .insert(Viewshed::new(7))
-        .insert(Actor::default())
+        .insert(Actor)
         .insert(BlocksMovement)
         .insert(Pushable);
 }
 
+/// Spawns Platino who just sits in his hiding spot and does not want to be moved
+fn platino(
+    pos: Position,
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+) {
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            texture_atlas: get_texture_atlas_handle(
+                "Dawnlike/Characters/Reptile0.png",
+                8,
+                13,
+                asset_server,
+                texture_atlases,
+            ),
+            transform: Transform::from_translation(Vec3::Z * ZBUF_CREATURES),
+            sprite: get_sprite(99),
+            ..default()
+        })
+        .insert(pos)
+        .insert(BlocksMovement);
+}
+
+/// Spawns a closed [`Door`] that blocks movement and sight
+fn door(
+    pos: Position,
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlas>,
+) {
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            texture_atlas: get_texture_atlas_handle(
+                "Dawnlike/Objects/Door0.png",
+                8,
+                6,
+                asset_server,
+                texture_atlases,
+            ),
+            transform: Transform::from_translation(Vec3::Z * ZBUF_ITEMS),
+            sprite: get_sprite(0),
+            ..default()
+        })
+        .insert(pos)
+        .insert(Door { open: false })
+        .insert(BlocksMovement)
+        .insert(BlocksVision);
+}
+
 /// Load the specified spritesheet at return a handle to the resulting [`TextureAtlas`]
-fn get_texture_atlas_handle(
+pub fn get_texture_atlas_handle(
     spritesheet_path: &str,
     columns: usize,
     rows: usize,
@@ -172,45 +236,44 @@ fn spawn_tiles(
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
-    let (floor_sprite, floor_atlas) = {
-        let floor_handle = asset_server.load("Dawnlike/Objects/Floor.png");
-        let floor_atlas =
-            TextureAtlas::from_grid(floor_handle, Vec2::new(SPRITE_SIZE, SPRITE_SIZE), 21, 39);
-        let floor_atlas_handle = texture_atlases.add(floor_atlas);
-        let mut sprite = TextureAtlasSprite::new(85);
-        sprite.custom_size = Some(Vec2::new(TILE_SIZE, TILE_SIZE));
-        (sprite, floor_atlas_handle)
-    };
-    let (wall_sprite, wall_atlas) = {
-        let wall_handle = asset_server.load("Dawnlike/Objects/Wall.png");
-        let wall_atlas =
-            TextureAtlas::from_grid(wall_handle, Vec2::new(SPRITE_SIZE, SPRITE_SIZE), 20, 51);
-        let wall_atlas_handle = texture_atlases.add(wall_atlas);
-        let mut sprite = TextureAtlasSprite::new(243);
-        sprite.custom_size = Some(Vec2::new(TILE_SIZE, TILE_SIZE));
-        (sprite, wall_atlas_handle)
-    };
+    let floor_atlas = get_texture_atlas_handle(
+        "Dawnlike/Objects/Floor.png",
+        21,
+        39,
+        &asset_server,
+        &mut texture_atlases,
+    );
+    let wall_atlas = get_texture_atlas_handle(
+        "Dawnlike/Objects/Wall.png",
+        20,
+        51,
+        &asset_server,
+        &mut texture_atlases,
+    );
+    let water_atlas = get_texture_atlas_handle(
+        "Dawnlike/Objects/Pit0.png",
+        8,
+        32,
+        &asset_server,
+        &mut texture_atlases,
+    );
     for (idx, tile) in map.tiles.iter().enumerate() {
         let (x, y) = map.idx_to_xy(idx).unwrap();
-        match tile {
-            TileType::Floor => commands
-                .spawn_bundle(SpriteSheetBundle {
-                    texture_atlas: floor_atlas.clone(),
-                    transform: Transform::from_translation(Vec3::Z * ZBUF_TILES),
-                    sprite: floor_sprite.clone(),
-                    ..default()
-                })
-                .insert(Position::new(x, y))
-                .insert(*tile),
-            TileType::Wall => commands
-                .spawn_bundle(SpriteSheetBundle {
-                    texture_atlas: wall_atlas.clone(),
-                    transform: Transform::from_translation(Vec3::Z * ZBUF_TILES),
-                    sprite: wall_sprite.clone(),
-                    ..default()
-                })
-                .insert(Position::new(x, y))
-                .insert(*tile),
+        let (atlas, sprite_index) = match tile {
+            TileType::Floor => (&floor_atlas, 85),
+            TileType::Wall => (&wall_atlas, 243),
+            TileType::Grass => (&floor_atlas, 155),
+            TileType::ShallowWater => (&water_atlas, 73),
+            TileType::DeepWater => (&water_atlas, 121),
         };
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                texture_atlas: atlas.clone(),
+                transform: Transform::from_translation(Vec3::Z * ZBUF_TILES),
+                sprite: get_sprite(sprite_index),
+                ..default()
+            })
+            .insert(Position::new(x, y))
+            .insert(*tile);
     }
 }
@@ -0,0 +1,236 @@
+use clap::{Parser, ValueEnum};
+use std::time::Instant;
+
+use crate::{
+    generate::{generate_levels, level_or_exit},
+    level::{GeneratedLevel, LevelSettings, MapBuilder},
+    map::GameMap,
+    map_builder::spawner::Spawnables,
+};
+
+/// Parameters of the `stats` subcommand in addition to the map related ones of the game
+#[derive(Parser, Debug)]
+pub struct StatsArgs {
+    /// Number of consecutive seeds to generate levels for, starting with the given seed
+    #[clap(short = 'n', long = "seeds", default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    seeds: u64,
+
+    /// Number of consecutive levels to generate per seed
+    #[clap(short = 'l', long = "levels", default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    levels: u32,
+
+    /// Comma-separated builders to compare, all but `random` if omitted (ignored for a builder chain)
+    #[clap(short = 'b', long = "builders", value_enum, value_delimiter = ',')]
+    builders: Vec<MapBuilder>,
+
+    /// How to print the statistics
+    #[clap(short = 'f', long = "format", value_enum, default_value = "table")]
+    format: ReportFormat,
+}
+
+/// Available output formats of the `stats` subcommand
+#[derive(Debug, clap::ValueEnum, Clone, Copy)]
+enum ReportFormat {
+    /// Mean, minimum and maximum of each statistic per builder
+    Table,
+    /// One row per generated level
+    Csv,
+}
+
+/// Statistics of a single generated level, see [`statistic_names`] for the order of `values`
+struct LevelReport {
+    builder: String,
+    style: String,
+    seed: u64,
+    depth: u32,
+    /// `None` if a statistic does not apply to the level, e.g. the number of rooms of a cave
+    values: Vec<Option<f64>>,
+}
+
+/// Names of the statistics collected for each level
+fn statistic_names() -> Vec<String> {
+    let names = [
+        "width",
+        "height",
+        "floor_ratio",
+        "rooms",
+        "regions",
+        "goal_distance",
+        "dead_ends",
+        "culled_tiles",
+    ];
+    let spawns = Spawnables::ALL.iter().map(|spawn| format!("{spawn:?}"));
+    names
+        .into_iter()
+        .map(String::from)
+        .chain(spawns)
+        .chain(["millis".to_string()])
+        .collect()
+}
+
+/// Generates levels for all selected builders and seeds and prints statistics on them
+pub fn run(args: &StatsArgs, settings: &LevelSettings) {
+    let builders: Vec<Option<MapBuilder>> = if settings.chain.is_some() {
+        vec![None]
+    } else if args.builders.is_empty() {
+        MapBuilder::value_variants()
+            .iter()
+            .filter(|builder| !matches!(builder, MapBuilder::Random))
+            .copied()
+            .map(Some)
+            .collect()
+    } else {
+        args.builders.iter().copied().map(Some).collect()
+    };
+
+    let mut reports = Vec::new();
+    for builder in builders {
+        let seeds = settings.original_seed..settings.original_seed.saturating_add(args.seeds);
+        for seed in seeds {
+            let settings = LevelSettings {
+                builder: builder.unwrap_or(settings.builder),
+                original_seed: seed,
+                ..settings.clone()
+            };
+            let mut levels = generate_levels(&settings, args.levels);
+            for depth in 1..=args.levels {
+                let started = Instant::now();
+                let level = level_or_exit(
+                    levels.next().expect("One level is generated per depth!"),
+                    depth,
+                );
+                let millis = started.elapsed().as_secs_f64() * 1000.0;
+                reports.push(LevelReport {
+                    builder: builder.map_or("Chain".to_string(), |b| format!("{b:?}")),
+                    style: level
+                        .style
+                        .map_or("Chain".to_string(), |s| format!("{s:?}")),
+                    seed,
+                    depth,
+                    values: level_statistics(&level, millis),
+                });
+            }
+        }
+    }
+
+    match args.format {
+        ReportFormat::Table => print_table(&reports),
+        ReportFormat::Csv => print_csv(&reports),
+    }
+}
+
+fn level_statistics(level: &GeneratedLevel, millis: f64) -> Vec<Option<f64>> {
+    let map = &level.map;
+    let metadata = &level.metadata;
+    let walkable = map.tiles.iter().filter(|t| t.is_walkable()).count();
+    let goal_distance = metadata.starting_position.and_then(|start| {
+        let path_lengths = map.path_lengths(start);
+        metadata
+            .spawn_list
+            .iter()
+            .filter(|(_, spawn)| spawn.is_objective())
+            .filter_map(|(pos, _)| path_lengths.get(pos))
+            .min()
+            .copied()
+    });
+
+    let mut values = vec![
+        Some(map.width as f64),
+        Some(map.height as f64),
+        Some(walkable as f64 / map.length() as f64),
+        metadata.rooms.as_ref().map(|rooms| rooms.len() as f64),
+        metadata
+            .regions
+            .as_ref()
+            .map(|regions| regions.len() as f64),
+        goal_distance.map(f64::from),
+        Some(dead_ends(map) as f64),
+        Some(metadata.culled_components.iter().sum::<usize>() as f64),
+    ];
+    values.extend(Spawnables::ALL.iter().map(|kind| {
+        let count = metadata.spawn_list.values().filter(|s| *s == kind).count();
+        Some(count as f64)
+    }));
+    values.push(Some(millis));
+    values
+}
+
+/// Counts walkable tiles with a single walkable neighbor, i.e. the ends of dead-end corridors
+fn dead_ends(map: &GameMap) -> usize {
+    (0..map.length())
+        .filter(|&idx| map.tiles[idx].is_walkable())
+        .filter(|&idx| map.walkable_neighbors(map.idx_to_xy(idx).unwrap()).len() == 1)
+        .count()
+}
+
+fn print_csv(reports: &[LevelReport]) {
+    println!("builder,style,seed,depth,{}", statistic_names().join(","));
+    for report in reports {
+        let values: Vec<String> = report
+            .values
+            .iter()
+            .map(|value| value.map_or(String::new(), |v| v.to_string()))
+            .collect();
+        println!(
+            "{},{},{},{},{}",
+            report.builder,
+            report.style,
+            report.seed,
+            report.depth,
+            values.join(",")
+        );
+    }
+}
+
+/// Prints one block per builder (in order of their first report) with a row per statistic
+fn print_table(reports: &[LevelReport]) {
+    let mut builders: Vec<&str> = Vec::new();
+    for report in reports {
+        if !builders.contains(&report.builder.as_str()) {
+            builders.push(&report.builder);
+        }
+    }
+
+    for builder in builders {
+        let reports: Vec<&LevelReport> = reports.iter().filter(|r| r.builder == builder).collect();
+        println!("{builder} ({} levels)", reports.len());
+        println!(
+            "  {:<16}{:>10}{:>10}{:>10}",
+            "statistic", "mean", "min", "max"
+        );
+        for (i, name) in statistic_names().iter().enumerate() {
+            let values: Vec<f64> = reports.iter().filter_map(|r| r.values[i]).collect();
+            if values.is_empty() {
+                println!("  {name:<16}{:>10}{:>10}{:>10}", "-", "-", "-");
+                continue;
+            }
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            println!("  {name:<16}{mean:>10.2}{min:>10.2}{max:>10.2}");
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+
+    #[test]
+    fn test_dead_ends() {
+        // A T-shaped corridor with three dead ends
+        let mut map = GameMap::new(7, 4);
+        for (x, y) in [(1, 2), (2, 2), (3, 2), (4, 2), (5, 2), (3, 1)] {
+            let idx = map.xy_to_idx(x, y).unwrap();
+            map.tiles[idx] = TileType::Floor;
+        }
+        assert_eq!(dead_ends(&map), 3);
+
+        // Connecting the stem back to the corridor removes its dead end
+        let idx = map.xy_to_idx(2, 1).unwrap();
+        map.tiles[idx] = TileType::Floor;
+        assert_eq!(dead_ends(&map), 2);
+    }
+}
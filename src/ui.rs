@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_egui::{egui, EguiContext};
 use iyes_loopless::{prelude::IntoConditionalSystem, state::NextState};
 
-use crate::{player::Player, GameState};
+use crate::{level::LevelStyle, player::Player, GameState};
 
 /// Bundles systems responsible for rendering
 #[derive(Debug)]
@@ -34,8 +34,14 @@ fn setup_ui_elements(mut ctx: ResMut<EguiContext>) {
     ctx.ctx_mut().set_fonts(fonts);
 }
 
-fn render_ui(mut ctx: ResMut<EguiContext>, player: Query<&Player>) {
+fn render_ui(mut ctx: ResMut<EguiContext>, player: Query<&Player>, style: Option<Res<LevelStyle>>) {
     egui::SidePanel::right("Right panel").show(ctx.ctx_mut(), |ui| {
+        if let Some(style) = style.as_ref().and_then(|s| s.0) {
+            ui.horizontal(|ui| {
+                ui.label("Level style: ");
+                ui.label(format!("{style:?}"));
+            });
+        }
         if let Ok(player) = player.get_single() {
             ui.horizontal(|ui| {
                 ui.label("Action points left: ");
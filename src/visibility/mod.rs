@@ -2,7 +2,7 @@ use bevy::prelude::*;
 
 use crate::{
     components::{Position, Viewshed},
-    map::{GameMap, TileType},
+    map::GameMap,
     player::Player,
 };
 
@@ -21,7 +21,7 @@ pub fn determine_visibility(
         let range = view.range;
         let is_blocking = |pos: Position| {
             if let Ok(idx) = map.xy_to_idx(pos.x, pos.y) {
-                map.tiles[idx] == TileType::Wall
+                map.opaque[idx]
             } else {
                 // Consider tiles outside the map as walls
                 true